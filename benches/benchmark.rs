@@ -1,7 +1,18 @@
-use criterion::{Criterion, criterion_group, criterion_main};
-use electron::{Queue, Stack};
-use std::collections::LinkedList;
+use criterion::{BatchSize, Criterion, criterion_group, criterion_main};
+use electron::backoff::{Backoff, Exponential, NoBackoff, SpinThenYield};
+use electron::epoch::EpochReclaimer;
+use electron::hashmap::HashMap;
+use electron::hazard::HazardReclaimer;
+use electron::list::Set;
+use electron::reclaim::Reclaimer;
+use electron::skipmap::SkipMap;
+use electron::sync::atomic::AtomicPtr;
+use electron::{BoxedPointer, Doer, Domain, EliminationStack, Holder, Queue, Stack};
+use std::collections::{BTreeMap, BTreeSet, HashSet, LinkedList};
 use std::sync::Mutex;
+use std::sync::atomic::Ordering;
+
+static DROPBOX: BoxedPointer = BoxedPointer::new();
 
 fn std_mutex_stack(threads: usize) {
     let new = &Mutex::new(LinkedList::new());
@@ -19,17 +30,17 @@ fn std_mutex_stack(threads: usize) {
     });
 }
 
-fn electron_stack(threads: usize) {
-    let new = &Stack::new();
+fn electron_stack<R: Reclaimer + Default>(threads: usize) {
+    let new = &Stack::<usize, R>::default();
     std::thread::scope(|s| {
         for i in 0..threads {
             s.spawn(move || {
-                let _ = new.insert(i);
+                new.push(i);
             });
         }
         for _ in 0..threads {
             s.spawn(move || {
-                let _ = new.delete();
+                let _ = new.pop();
             });
         }
     });
@@ -51,8 +62,8 @@ fn std_mutex_queue(threads: usize) {
     });
 }
 
-fn electron_queue(threads: usize) {
-    let new = &Queue::new();
+fn electron_queue<R: Reclaimer + Default>(threads: usize) {
+    let new = &Queue::<usize, R>::default();
     std::thread::scope(|s| {
         for i in 0..threads {
             s.spawn(move || {
@@ -67,12 +78,265 @@ fn electron_queue(threads: usize) {
     });
 }
 
+// Every thread inserts its own keys, looks them up and removes every other one again.
+fn std_mutex_set(threads: usize, keys: usize) {
+    let set = &Mutex::new(BTreeSet::new());
+    std::thread::scope(|s| {
+        for t in 0..threads {
+            s.spawn(move || {
+                for i in 0..keys {
+                    let key = i * threads + t;
+                    set.lock().unwrap().insert(key);
+                    std::hint::black_box(set.lock().unwrap().contains(&key));
+                    if i % 2 == 0 {
+                        set.lock().unwrap().remove(&key);
+                    }
+                }
+            });
+        }
+    });
+}
+
+fn electron_set(threads: usize, keys: usize) {
+    let set = &Set::new();
+    std::thread::scope(|s| {
+        for t in 0..threads {
+            s.spawn(move || {
+                for i in 0..keys {
+                    let key = i * threads + t;
+                    set.insert(key);
+                    std::hint::black_box(set.contains(&key));
+                    if i % 2 == 0 {
+                        set.remove(&key);
+                    }
+                }
+            });
+        }
+    });
+}
+
+fn std_mutex_map(threads: usize, keys: usize) {
+    let map = &Mutex::new(std::collections::HashMap::new());
+    std::thread::scope(|s| {
+        for t in 0..threads {
+            s.spawn(move || {
+                for i in 0..keys {
+                    let key = i * threads + t;
+                    map.lock().unwrap().insert(key, i);
+                    std::hint::black_box(map.lock().unwrap().get(&key).copied());
+                    if i % 2 == 0 {
+                        map.lock().unwrap().remove(&key);
+                    }
+                }
+            });
+        }
+    });
+}
+
+fn electron_map(threads: usize, keys: usize) {
+    let map = &HashMap::new();
+    std::thread::scope(|s| {
+        for t in 0..threads {
+            s.spawn(move || {
+                for i in 0..keys {
+                    let key = i * threads + t;
+                    map.insert(key, i);
+                    std::hint::black_box(map.get(&key).map(|value| *value));
+                    if i % 2 == 0 {
+                        map.remove(&key);
+                    }
+                }
+            });
+        }
+    });
+}
+
+fn std_mutex_ordered_map(threads: usize, keys: usize) {
+    let map = &Mutex::new(BTreeMap::new());
+    std::thread::scope(|s| {
+        for t in 0..threads {
+            s.spawn(move || {
+                for i in 0..keys {
+                    let key = i * threads + t;
+                    map.lock().unwrap().insert(key, i);
+                    std::hint::black_box(map.lock().unwrap().get(&key).copied());
+                    if i % 2 == 0 {
+                        map.lock().unwrap().remove(&key);
+                    }
+                    if i % 10 == 0 {
+                        let sum: usize = map
+                            .lock()
+                            .unwrap()
+                            .range(key..key + 100)
+                            .map(|(_, v)| v)
+                            .sum();
+                        std::hint::black_box(sum);
+                    }
+                }
+            });
+        }
+    });
+}
+
+fn electron_ordered_map(threads: usize, keys: usize) {
+    let map = &SkipMap::new();
+    std::thread::scope(|s| {
+        for t in 0..threads {
+            s.spawn(move || {
+                for i in 0..keys {
+                    let key = i * threads + t;
+                    map.insert(key, i);
+                    std::hint::black_box(map.get(&key).map(|value| *value));
+                    if i % 2 == 0 {
+                        map.remove(&key);
+                    }
+                    if i % 10 == 0 {
+                        let sum: usize =
+                            map.range(key..key + 100).map(|entry| *entry.value()).sum();
+                        std::hint::black_box(sum);
+                    }
+                }
+            });
+        }
+    });
+}
+
+// Every thread pushes and pops in turns, so that nodes are retired and reallocated all the time.
+fn electron_queue_churn(queue: &Queue<usize>, threads: usize, operations: usize) {
+    std::thread::scope(|s| {
+        for _ in 0..threads {
+            s.spawn(move || {
+                for i in 0..operations {
+                    queue.enqueue(i);
+                    let _ = std::hint::black_box(queue.dequeue());
+                }
+            });
+        }
+    });
+}
+
+fn electron_stack_churn<R: Reclaimer, B: Backoff>(
+    stack: &Stack<usize, R, B>,
+    threads: usize,
+    operations: usize,
+) {
+    std::thread::scope(|s| {
+        for _ in 0..threads {
+            s.spawn(move || {
+                for i in 0..operations {
+                    stack.push(i);
+                    std::hint::black_box(stack.pop());
+                }
+            });
+        }
+    });
+}
+
+fn std_mutex_stack_churn(stack: &Mutex<LinkedList<usize>>, threads: usize, operations: usize) {
+    std::thread::scope(|s| {
+        for _ in 0..threads {
+            s.spawn(move || {
+                for i in 0..operations {
+                    stack.lock().unwrap().push_front(i);
+                    std::hint::black_box(stack.lock().unwrap().pop_front());
+                }
+            });
+        }
+    });
+}
+
+fn electron_elimination_churn(stack: &EliminationStack<usize>, threads: usize, operations: usize) {
+    std::thread::scope(|s| {
+        for _ in 0..threads {
+            s.spawn(move || {
+                for i in 0..operations {
+                    stack.push(i);
+                    std::hint::black_box(stack.pop());
+                }
+            });
+        }
+    });
+}
+
+// The scans below all get the same hazards and reclaim the same number of freshly retired
+// pointers, none of which are protected. Only the scan itself is timed, retiring is done in setup.
+fn retired(retirements: usize) -> Vec<*mut usize> {
+    (0..retirements)
+        .map(|i| Box::into_raw(Box::new(i)))
+        .collect()
+}
+
+// The scan reclaim used to do, with a fresh HashSet of the hazards for every scan.
+fn hashset_scan(hazards: &[*mut ()], retired: &[*mut usize]) {
+    let protected: HashSet<*mut ()> = hazards.iter().copied().collect();
+    for ptr in retired {
+        if !protected.contains(&(*ptr as *mut ())) {
+            let _ = unsafe { Box::from_raw(*ptr) };
+        }
+    }
+}
+
+// The scan reclaim does now, over a buffer that is kept across scans and sorted once per scan.
+fn sorted_scan(hazards: &[*mut ()], retired: &[*mut usize], buffer: &mut Vec<*mut ()>) {
+    buffer.clear();
+    buffer.extend_from_slice(hazards);
+    buffer.sort_unstable();
+    for ptr in retired {
+        if buffer.binary_search(&(*ptr as *mut ())).is_err() {
+            let _ = unsafe { Box::from_raw(*ptr) };
+        }
+    }
+}
+
+// Hands the pointers to a domain, whose scan also walks its hazard slots and retired list.
+fn retire_all(holder: &mut Holder, retired: &[*mut usize]) {
+    for ptr in retired {
+        let atomic = AtomicPtr::new(*ptr);
+        if let Some(mut wrapper) = unsafe { holder.get_wrapper(&atomic, &DROPBOX) } {
+            wrapper.retire();
+        }
+    }
+}
+
+// Protect-heavy readers with one thread retiring. Compare the default build against
+// `--features seqcst-fence` and `--features asymmetric-fence` to measure the fence cost.
+fn electron_load(threads: usize, loads: usize) {
+    let value = &AtomicPtr::new(Box::into_raw(Box::new(0usize)));
+    std::thread::scope(|s| {
+        for _ in 0..threads {
+            s.spawn(move || {
+                let mut holder = Holder::default();
+                for _ in 0..loads {
+                    if let Some(guard) = unsafe { holder.load_pointer(value) } {
+                        std::hint::black_box(*guard);
+                    }
+                }
+            });
+        }
+        s.spawn(move || {
+            let mut holder = Holder::default();
+            for i in 0..threads {
+                let new = Box::into_raw(Box::new(i));
+                if let Some(mut wrapper) = unsafe { holder.swap(value, new, &DROPBOX) } {
+                    wrapper.retire();
+                }
+            }
+        });
+    });
+    let _ = unsafe { Box::from_raw(value.load(Ordering::Acquire)) };
+}
+
 macro_rules! generate_stack_benchmark {
     ($name: ident, $number: expr) => {
         fn $name(c: &mut Criterion) {
             let mut group = c.benchmark_group("Bravo");
             group.bench_function("Std_stack", |b| b.iter(|| std_mutex_stack($number)));
-            group.bench_function("Electron_stack", |b| b.iter(|| electron_stack($number)));
+            group.bench_function("Electron_stack", |b| {
+                b.iter(|| electron_stack::<HazardReclaimer>($number))
+            });
+            group.bench_function("Electron_epoch_stack", |b| {
+                b.iter(|| electron_stack::<EpochReclaimer>($number))
+            });
             group.finish();
         }
     };
@@ -83,15 +347,205 @@ macro_rules! generate_queue_benchmark {
         fn $name(c: &mut Criterion) {
             let mut group = c.benchmark_group("Delta");
             group.bench_function("Std_queue", |b| b.iter(|| std_mutex_queue($number)));
-            group.bench_function("Electron_queue", |b| b.iter(|| electron_queue($number)));
+            group.bench_function("Electron_queue", |b| {
+                b.iter(|| electron_queue::<HazardReclaimer>($number))
+            });
+            group.bench_function("Electron_epoch_queue", |b| {
+                b.iter(|| electron_queue::<EpochReclaimer>($number))
+            });
+            group.finish();
+        }
+    };
+}
+macro_rules! generate_scan_benchmark {
+    ($name: ident, $number: expr) => {
+        fn $name(c: &mut Criterion) {
+            // Never reached by the retirements below, so the domain only scans when asked to.
+            let domain = Domain::with_threshold(1 << 20);
+            let values: Vec<AtomicPtr<usize>> = (0..$number)
+                .map(|i| AtomicPtr::new(Box::into_raw(Box::new(i))))
+                .collect();
+            let mut holders: Vec<Holder> = (0..$number).map(|_| Holder::new_in(&domain)).collect();
+            let guards: Vec<_> = holders
+                .iter_mut()
+                .zip(values.iter())
+                .map(|(holder, value)| unsafe { holder.load_pointer(value) })
+                .collect();
+            let hazards: Vec<*mut ()> = guards
+                .iter()
+                .flatten()
+                .map(|guard| guard.as_ptr() as *mut ())
+                .collect();
+            let mut group = c.benchmark_group("Echo");
+            group.bench_function("HashSet_scan", |b| {
+                b.iter_batched_ref(
+                    || retired(1000),
+                    |retired| hashset_scan(&hazards, retired),
+                    BatchSize::PerIteration,
+                )
+            });
+            let mut buffer = Vec::new();
+            group.bench_function("Sorted_scan", |b| {
+                b.iter_batched_ref(
+                    || retired(1000),
+                    |retired| sorted_scan(&hazards, retired, &mut buffer),
+                    BatchSize::PerIteration,
+                )
+            });
+            let mut holder = Holder::new_in(&domain);
+            group.bench_function("Electron_scan", |b| {
+                b.iter_batched(
+                    || retire_all(&mut holder, &retired(1000)),
+                    |()| domain.try_reclaim(),
+                    BatchSize::PerIteration,
+                )
+            });
             group.finish();
+            std::mem::drop(guards);
+            for value in values {
+                let _ = unsafe { Box::from_raw(value.into_inner()) };
+            }
         }
     };
 }
+
+macro_rules! generate_load_benchmark {
+    ($name: ident, $number: expr) => {
+        fn $name(c: &mut Criterion) {
+            let mut group = c.benchmark_group("Foxtrot");
+            group.bench_function("Electron_load", |b| {
+                b.iter(|| electron_load($number, 10_000))
+            });
+            group.finish();
+        }
+    };
+}
+
+macro_rules! generate_set_benchmark {
+    ($name: ident, $number: expr) => {
+        fn $name(c: &mut Criterion) {
+            let mut group = c.benchmark_group("Golf");
+            group.bench_function("Std_set", |b| b.iter(|| std_mutex_set($number, 100)));
+            group.bench_function("Electron_set", |b| b.iter(|| electron_set($number, 100)));
+            group.finish();
+        }
+    };
+}
+
+macro_rules! generate_map_benchmark {
+    ($name: ident, $number: expr) => {
+        fn $name(c: &mut Criterion) {
+            let mut group = c.benchmark_group("Hotel");
+            group.bench_function("Std_map", |b| b.iter(|| std_mutex_map($number, 1000)));
+            group.bench_function("Electron_map", |b| b.iter(|| electron_map($number, 1000)));
+            group.finish();
+        }
+    };
+}
+
+macro_rules! generate_ordered_map_benchmark {
+    ($name: ident, $number: expr) => {
+        fn $name(c: &mut Criterion) {
+            let mut group = c.benchmark_group("India");
+            group.bench_function("Std_ordered_map", |b| {
+                b.iter(|| std_mutex_ordered_map($number, 1000))
+            });
+            group.bench_function("Electron_ordered_map", |b| {
+                b.iter(|| electron_ordered_map($number, 1000))
+            });
+            group.finish();
+        }
+    };
+}
+
+macro_rules! generate_pool_benchmark {
+    ($name: ident, $number: expr) => {
+        fn $name(c: &mut Criterion) {
+            let mut group = c.benchmark_group("Juliet");
+            let queue = Queue::new();
+            group.bench_function("Boxed_queue", |b| {
+                b.iter(|| electron_queue_churn(&queue, $number, 1000))
+            });
+            let queue = Queue::with_pool(1024);
+            group.bench_function("Pooled_queue", |b| {
+                b.iter(|| electron_queue_churn(&queue, $number, 1000))
+            });
+            let stack = Stack::new();
+            group.bench_function("Boxed_stack", |b| {
+                b.iter(|| electron_stack_churn(&stack, $number, 1000))
+            });
+            let stack = Stack::with_pool(1024);
+            group.bench_function("Pooled_stack", |b| {
+                b.iter(|| electron_stack_churn(&stack, $number, 1000))
+            });
+            group.finish();
+        }
+    };
+}
+
+macro_rules! generate_backoff_benchmark {
+    ($name: ident, $number: expr) => {
+        fn $name(c: &mut Criterion) {
+            let mut group = c.benchmark_group("Kilo");
+            let stack = Stack::<usize, HazardReclaimer, Exponential>::default();
+            group.bench_function("Exponential_stack", |b| {
+                b.iter(|| electron_stack_churn(&stack, $number, 1000))
+            });
+            let stack = Stack::<usize, HazardReclaimer, SpinThenYield>::default();
+            group.bench_function("Spin_then_yield_stack", |b| {
+                b.iter(|| electron_stack_churn(&stack, $number, 1000))
+            });
+            let stack = Stack::<usize, HazardReclaimer, NoBackoff>::default();
+            group.bench_function("No_backoff_stack", |b| {
+                b.iter(|| electron_stack_churn(&stack, $number, 1000))
+            });
+            group.finish();
+        }
+    };
+}
+
+macro_rules! generate_elimination_benchmark {
+    ($name: ident, $number: expr) => {
+        fn $name(c: &mut Criterion) {
+            let mut group = c.benchmark_group("Lima");
+            let stack = Mutex::new(LinkedList::new());
+            group.bench_function("Std_stack", |b| {
+                b.iter(|| std_mutex_stack_churn(&stack, $number, 1000))
+            });
+            let stack = Stack::new();
+            group.bench_function("Electron_stack", |b| {
+                b.iter(|| electron_stack_churn(&stack, $number, 1000))
+            });
+            let stack = EliminationStack::new();
+            group.bench_function("Elimination_stack", |b| {
+                b.iter(|| electron_elimination_churn(&stack, $number, 1000))
+            });
+            group.finish();
+        }
+    };
+}
+
 generate_stack_benchmark!(benchmark1, 10);
 generate_stack_benchmark!(benchmark2, 100);
 generate_queue_benchmark!(benchmark3, 10);
 generate_queue_benchmark!(benchmark4, 100);
+generate_scan_benchmark!(benchmark5, 8);
+generate_scan_benchmark!(benchmark6, 64);
+generate_load_benchmark!(benchmark7, 4);
+generate_load_benchmark!(benchmark8, 16);
+generate_set_benchmark!(benchmark9, 4);
+generate_set_benchmark!(benchmark10, 16);
+generate_map_benchmark!(benchmark11, 4);
+generate_map_benchmark!(benchmark12, 16);
+generate_ordered_map_benchmark!(benchmark13, 4);
+generate_ordered_map_benchmark!(benchmark14, 16);
+generate_pool_benchmark!(benchmark15, 4);
+generate_pool_benchmark!(benchmark16, 16);
+generate_backoff_benchmark!(benchmark17, 4);
+generate_backoff_benchmark!(benchmark18, 16);
+generate_elimination_benchmark!(benchmark19, 8);
+generate_elimination_benchmark!(benchmark20, 16);
+generate_elimination_benchmark!(benchmark21, 64);
 
-criterion_group! {name = benchmarks; config = Criterion::default(); targets = benchmark1, benchmark2, benchmark3, benchmark4}
+criterion_group! {name = benchmarks; config = Criterion::default(); targets = benchmark1, benchmark2, benchmark3, benchmark4, benchmark5, benchmark6, benchmark7, benchmark8, benchmark9, benchmark10, benchmark11, benchmark12, benchmark13, benchmark14, benchmark15, benchmark16, benchmark17, benchmark18, benchmark19, benchmark20, benchmark21}
 criterion_main!(benchmarks);
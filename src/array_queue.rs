@@ -0,0 +1,173 @@
+use crate::error::PushError;
+use crate::sync::atomic::{AtomicUsize, fence};
+use std::cell::UnsafeCell;
+use std::mem::MaybeUninit;
+use std::sync::atomic::Ordering;
+
+struct Slot<T> {
+    // Equal to the position a push may claim the slot at, and to that position plus one once the
+    // value is written and a pop may claim it. Popping moves it on to the same index a lap later.
+    stamp: AtomicUsize,
+    value: UnsafeCell<MaybeUninit<T>>,
+}
+
+/// A bounded multi-producer multi-consumer queue on a ring buffer, after Vyukov. Producers and
+/// consumers claim positions by moving the tail and the head forward, and the stamp of every slot
+/// tells whose turn it is, so neither side ever waits on a lock. All memory is allocated up front.
+///
+/// Positions keep the index into the buffer in their low bits and count laps above them, so that
+/// a slot written in this lap is told apart from one freed in the last even with a single slot.
+pub struct ArrayQueue<T> {
+    buffer: Box<[Slot<T>]>,
+    // The smallest power of two above the capacity, the step from one lap to the next.
+    one_lap: usize,
+    head: AtomicUsize,
+    tail: AtomicUsize,
+}
+
+unsafe impl<T: Send> Send for ArrayQueue<T> {}
+unsafe impl<T: Send> Sync for ArrayQueue<T> {}
+
+impl<T> Drop for ArrayQueue<T> {
+    fn drop(&mut self) {
+        while self.pop().is_some() {}
+    }
+}
+
+impl<T> ArrayQueue<T> {
+    /// Panics if the capacity is zero.
+    pub fn new(capacity: usize) -> Self {
+        assert!(
+            capacity > 0,
+            "An ArrayQueue needs room for at least one value"
+        );
+        Self {
+            buffer: (0..capacity)
+                .map(|i| Slot {
+                    stamp: AtomicUsize::new(i),
+                    value: UnsafeCell::new(MaybeUninit::uninit()),
+                })
+                .collect(),
+            one_lap: (capacity + 1).next_power_of_two(),
+            head: AtomicUsize::new(0),
+            tail: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.buffer.len()
+    }
+
+    fn index(&self, position: usize) -> usize {
+        position & (self.one_lap - 1)
+    }
+
+    // The position after the given one, wrapping to the start of the next lap at the end of the
+    // buffer.
+    fn advance(&self, position: usize) -> usize {
+        if self.index(position) + 1 < self.capacity() {
+            position + 1
+        } else {
+            (position & !(self.one_lap - 1)).wrapping_add(self.one_lap)
+        }
+    }
+
+    /// Hands the value back if the queue is full.
+    pub fn push(&self, value: T) -> Result<(), PushError<T>> {
+        let mut tail = self.tail.load(Ordering::Relaxed);
+        loop {
+            let slot = &self.buffer[self.index(tail)];
+            let stamp = slot.stamp.load(Ordering::Acquire);
+            if stamp == tail {
+                match self.tail.compare_exchange_weak(
+                    tail,
+                    self.advance(tail),
+                    Ordering::SeqCst,
+                    Ordering::Relaxed,
+                ) {
+                    Ok(_) => {
+                        unsafe { (*slot.value.get()).write(value) };
+                        slot.stamp.store(tail + 1, Ordering::Release);
+                        return Ok(());
+                    }
+                    Err(now) => tail = now,
+                }
+            } else if stamp.wrapping_add(self.one_lap) == tail + 1 {
+                // The slot still holds the value pushed a lap ago, which means the queue is full
+                // unless that value has been claimed by a pop in the meantime.
+                fence(Ordering::SeqCst);
+                if self.head.load(Ordering::Relaxed).wrapping_add(self.one_lap) == tail {
+                    return Err(PushError::Full(value));
+                }
+                tail = self.tail.load(Ordering::Relaxed);
+            } else {
+                tail = self.tail.load(Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// Returns None if the queue is empty.
+    pub fn pop(&self) -> Option<T> {
+        let mut head = self.head.load(Ordering::Relaxed);
+        loop {
+            let slot = &self.buffer[self.index(head)];
+            let stamp = slot.stamp.load(Ordering::Acquire);
+            if stamp == head + 1 {
+                match self.head.compare_exchange_weak(
+                    head,
+                    self.advance(head),
+                    Ordering::SeqCst,
+                    Ordering::Relaxed,
+                ) {
+                    Ok(_) => {
+                        let value = unsafe { (*slot.value.get()).assume_init_read() };
+                        slot.stamp
+                            .store(head.wrapping_add(self.one_lap), Ordering::Release);
+                        return Some(value);
+                    }
+                    Err(now) => head = now,
+                }
+            } else if stamp == head {
+                // Nothing has been written at this position yet, which means the queue is empty
+                // unless a push has claimed it in the meantime.
+                fence(Ordering::SeqCst);
+                if self.tail.load(Ordering::Relaxed) == head {
+                    return None;
+                }
+                head = self.head.load(Ordering::Relaxed);
+            } else {
+                head = self.head.load(Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// Only a snapshot while other threads push and pop.
+    pub fn len(&self) -> usize {
+        loop {
+            let tail = self.tail.load(Ordering::SeqCst);
+            let head = self.head.load(Ordering::SeqCst);
+            // Both have to be read without the tail moving in between for the difference to mean
+            // anything.
+            if self.tail.load(Ordering::SeqCst) == tail {
+                let (head_index, tail_index) = (self.index(head), self.index(tail));
+                return if head_index < tail_index {
+                    tail_index - head_index
+                } else if head_index > tail_index {
+                    self.capacity() - head_index + tail_index
+                } else if head == tail {
+                    0
+                } else {
+                    self.capacity()
+                };
+            }
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.len() == self.capacity()
+    }
+}
@@ -0,0 +1,124 @@
+use crate::hazard::Domain;
+use crate::threadpool::ThreadPool;
+use std::ops::Deref;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+// The domain a reclaimer scans, which it keeps alive for as long as it runs.
+#[derive(Clone)]
+enum Target {
+    Global,
+    Shared(Arc<Domain>),
+}
+
+impl Deref for Target {
+    type Target = Domain;
+    fn deref(&self) -> &Domain {
+        match self {
+            Target::Global => Domain::global(),
+            Target::Shared(domain) => domain,
+        }
+    }
+}
+
+/// Takes scanning off the retiring threads of a domain. Once the reclaimer is running, retiring
+/// only pushes onto the retired list, and crossing the threshold wakes the reclaimer instead of
+/// scanning inline. Until then, such as while the workers of a pool are busy, retiring threads
+/// keep scanning themselves. The reclaimer also scans once every interval on its own. Dropping
+/// the handle stops it and hands scanning back to the retiring threads.
+///
+/// A domain should have at most one background reclaimer at a time.
+pub struct BackgroundReclaimer {
+    domain: Target,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Drop for BackgroundReclaimer {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Release);
+        self.domain.wake_reclaimer();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl BackgroundReclaimer {
+    /// Runs the reclaimer on a dedicated thread. Data structures retire into a shared domain when
+    /// they are created with HazardReclaimer::with_shared_domain.
+    pub fn start(domain: Arc<Domain>, interval: Duration) -> Self {
+        Self::spawn(Target::Shared(domain), interval)
+    }
+
+    /// Runs the reclaimer of the process wide domain on a dedicated thread.
+    pub fn start_global(interval: Duration) -> Self {
+        Self::spawn(Target::Global, interval)
+    }
+
+    /// Runs the reclaimer as a long lived task on one of the workers of the pool, which it keeps
+    /// busy until either the handle is dropped or the pool shuts down.
+    pub fn start_on(pool: &ThreadPool, domain: Arc<Domain>, interval: Duration) -> Self {
+        Self::spawn_on(pool, Target::Shared(domain), interval)
+    }
+
+    /// Same as start_on, for the process wide domain.
+    pub fn start_global_on(pool: &ThreadPool, interval: Duration) -> Self {
+        Self::spawn_on(pool, Target::Global, interval)
+    }
+
+    fn spawn(domain: Target, interval: Duration) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let flag = Arc::clone(&stop);
+        let target = domain.clone();
+        let thread =
+            thread::spawn(move || run(&target, interval, || !flag.load(Ordering::Acquire)));
+        Self {
+            domain,
+            stop,
+            thread: Some(thread),
+        }
+    }
+
+    fn spawn_on(pool: &ThreadPool, domain: Target, interval: Duration) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let flag = Arc::clone(&stop);
+        let execution = pool.execution();
+        let target = domain.clone();
+        pool.execute_task(move || {
+            run(&target, interval, || {
+                !flag.load(Ordering::Acquire) && execution.load(Ordering::Relaxed)
+            })
+        });
+        Self {
+            domain,
+            stop,
+            thread: None,
+        }
+    }
+
+    pub fn domain(&self) -> &Domain {
+        &self.domain
+    }
+
+    /// Stops the reclaimer, see Drop.
+    pub fn stop(self) {
+        std::mem::drop(self);
+    }
+}
+
+fn run(domain: &Domain, interval: Duration, running: impl Fn() -> bool) {
+    // Registered first, so that no retirement that relies on waking the reclaimer misses it.
+    domain.set_reclaimer(Some(thread::current()));
+    domain.offload(true);
+    while running() {
+        domain.background_reclaim();
+        thread::park_timeout(interval);
+    }
+    domain.offload(false);
+    domain.set_reclaimer(None);
+    // Whatever got retired since the last scan would otherwise wait for the next retirement.
+    domain.try_reclaim();
+}
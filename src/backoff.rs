@@ -0,0 +1,58 @@
+use crate::sync::thread::yield_now;
+
+/// How a thread waits before retrying a compare and swap it lost. A fresh value is created for
+/// every operation, and snooze is called after each failed attempt.
+pub trait Backoff: Default {
+    fn snooze(&mut self);
+}
+
+// Beyond 2^10 iterations spinning costs more than the contention it avoids.
+const SPIN_LIMIT: u32 = 10;
+// Beyond 2^6 iterations the other threads are better off getting the processor.
+const YIELD_LIMIT: u32 = 6;
+
+fn spin(step: u32) {
+    for _ in 0..1 << step {
+        std::hint::spin_loop();
+    }
+}
+
+/// Spins for twice as long after every failed attempt, up to a limit. This spreads contending
+/// threads out in time, so that they stop invalidating the cache line of the head for each other.
+#[derive(Debug, Default)]
+pub struct Exponential {
+    step: u32,
+}
+
+impl Backoff for Exponential {
+    fn snooze(&mut self) {
+        spin(self.step);
+        self.step = (self.step + 1).min(SPIN_LIMIT);
+    }
+}
+
+/// Spins exponentially for the first few attempts and yields the thread from then on, which suits
+/// machines with more threads than cores.
+#[derive(Debug, Default)]
+pub struct SpinThenYield {
+    step: u32,
+}
+
+impl Backoff for SpinThenYield {
+    fn snooze(&mut self) {
+        if self.step < YIELD_LIMIT {
+            spin(self.step);
+            self.step += 1;
+        } else {
+            yield_now();
+        }
+    }
+}
+
+/// Retries right away.
+#[derive(Debug, Default)]
+pub struct NoBackoff;
+
+impl Backoff for NoBackoff {
+    fn snooze(&mut self) {}
+}
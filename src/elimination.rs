@@ -0,0 +1,164 @@
+use crate::backoff::NoBackoff;
+use crate::error::PopError;
+use crate::hazard::{HazardReclaimer, Retirable, RetireLink};
+use crate::random;
+use crate::reclaim::{Reclaimer, Shield};
+use crate::stack::{Node, Stack};
+use crate::sync::atomic::{AtomicPtr, AtomicUsize};
+use std::sync::atomic::Ordering;
+
+const WAITING: usize = 0;
+const TAKEN: usize = 1;
+const CANCELLED: usize = 2;
+
+// How many times a pusher checks on its offer before withdrawing it.
+const PATIENCE: usize = 128;
+
+// A push waiting in the elimination array for a pop to take its node.
+struct Offer<T> {
+    node: *mut Node<T>,
+    state: AtomicUsize,
+    link: RetireLink,
+}
+
+unsafe impl<T> Retirable for Offer<T> {
+    fn retire_link(&self) -> &RetireLink {
+        &self.link
+    }
+}
+
+/// A Stack with an elimination array in front of it, after Hendler, Shavit and Yerushalmi. A push
+/// or pop that loses the race for the head does not back off but goes to a random slot of the
+/// array instead, where a push waits for a pop to hand its value to directly. Pairs that meet
+/// there cancel out without ever touching the head, so contention spreads over the slots.
+///
+/// Offers are retired through the reclaimer of the stack, and pops protect them with its shields.
+pub struct EliminationStack<T, R: Reclaimer = HazardReclaimer> {
+    stack: Stack<T, R, NoBackoff>,
+    slots: Box<[AtomicPtr<Offer<T>>]>,
+}
+
+unsafe impl<T, R: Reclaimer> Send for EliminationStack<T, R> where T: Send {}
+unsafe impl<T, R: Reclaimer> Sync for EliminationStack<T, R> where T: Send {}
+
+impl<T> Default for EliminationStack<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> EliminationStack<T> {
+    /// Sizes the array by the number of threads the machine runs in parallel.
+    pub fn new() -> Self {
+        let width = std::thread::available_parallelism().map_or(1, |threads| threads.get() / 2);
+        Self::with_width(width.max(1))
+    }
+
+    pub fn with_width(width: usize) -> Self {
+        Self::with_reclaimer(HazardReclaimer::new(), width)
+    }
+}
+
+impl<T, R: Reclaimer> EliminationStack<T, R> {
+    /// Panics if the width is zero.
+    pub fn with_reclaimer(reclaimer: R, width: usize) -> Self {
+        assert!(width > 0, "The elimination array needs at least one slot");
+        Self {
+            stack: Stack::with_reclaimer(reclaimer),
+            slots: (0..width)
+                .map(|_| AtomicPtr::new(std::ptr::null_mut()))
+                .collect(),
+        }
+    }
+
+    pub fn reclaimer(&self) -> &R {
+        self.stack.reclaimer()
+    }
+
+    pub fn push(&self, value: T) {
+        let node = self.stack.new_node(value);
+        let mut current_head = self.stack.head();
+        loop {
+            match unsafe { self.stack.link(node, current_head) } {
+                Ok(_) => return,
+                Err(now) => current_head = now,
+            }
+            if unsafe { self.offer(node) } {
+                return;
+            }
+        }
+    }
+
+    /// Returns None once the stack is found empty.
+    pub fn pop(&self) -> Option<T> {
+        loop {
+            match self.stack.unlink() {
+                Ok(value) => return Some(value),
+                Err(PopError::Empty) => return None,
+                Err(PopError::Contended) => {}
+            }
+            if let Some(value) = self.take() {
+                return Some(value);
+            }
+        }
+    }
+
+    fn slot(&self) -> &AtomicPtr<Offer<T>> {
+        &self.slots[random::next() as usize % self.slots.len()]
+    }
+
+    // Waits for a pop to take the node, returning whether one did.
+    //
+    // SAFETY:
+    //    The node must not have been published yet.
+    unsafe fn offer(&self, node: *mut Node<T>) -> bool {
+        let slot = self.slot();
+        let offer = Box::into_raw(Box::new(Offer {
+            node,
+            state: AtomicUsize::new(WAITING),
+            link: RetireLink::new(),
+        }));
+        if slot
+            .compare_exchange(
+                std::ptr::null_mut(),
+                offer,
+                Ordering::AcqRel,
+                Ordering::Relaxed,
+            )
+            .is_err()
+        {
+            let owned = unsafe { Box::from_raw(offer) };
+            std::mem::drop(owned);
+            return false;
+        }
+        let state = unsafe { &(*offer).state };
+        for _ in 0..PATIENCE {
+            if state.load(Ordering::Acquire) == TAKEN {
+                break;
+            }
+            std::hint::spin_loop();
+        }
+        // Either the withdrawal wins, or a pop took the node just before.
+        let taken = state
+            .compare_exchange(WAITING, CANCELLED, Ordering::AcqRel, Ordering::Acquire)
+            .is_err();
+        // Only the pusher ever clears its slot, pops merely look at it.
+        slot.store(std::ptr::null_mut(), Ordering::Release);
+        unsafe { self.stack.reclaimer().retire(offer) };
+        taken
+    }
+
+    fn take(&self) -> Option<T> {
+        let mut shield = self.stack.reclaimer().shield();
+        let offer = shield.protect(self.slot());
+        if offer.is_null()
+            || unsafe { &(*offer).state }
+                .compare_exchange(WAITING, TAKEN, Ordering::AcqRel, Ordering::Relaxed)
+                .is_err()
+        {
+            return None;
+        }
+        // Winning the offer hands over its node, which was never published.
+        Some(unsafe { Node::into_value((*offer).node) })
+    }
+}
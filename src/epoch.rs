@@ -0,0 +1,445 @@
+#![allow(unexpected_cfgs)]
+
+use crate::hazard::{Cohort, Retirable, RetireLink};
+use crate::reclaim::{Reclaimer, Shield, Shields};
+use crate::sync::atomic::{AtomicBool, AtomicPtr, AtomicUsize, fence};
+use crate::sync::thread;
+#[cfg(not(loom))]
+use std::cell::RefCell;
+use std::marker::PhantomData;
+use std::sync::atomic::Ordering;
+
+#[cfg(loom)]
+loom::lazy_static! {
+    static ref SHARED_COLLECTOR: Collector = Collector::new();
+}
+
+#[cfg(not(loom))]
+static SHARED_COLLECTOR: Collector = Collector::new();
+
+// An object retired while the global epoch is e may still be reachable by threads pinned in e - 1
+// or e, and nobody can be pinned in either of those any more once the epoch has reached e + 2. So
+// when the epoch moves from e to e + 1, everything retired in e - 1 can go, and three garbage
+// lists indexed by the epoch modulo three are all that is needed.
+const GARBAGE_LISTS: usize = 3;
+
+// The retiring thread tries to advance the epoch once every this many retirements.
+const COLLECT_INTERVAL: usize = 64;
+
+const PINNED: usize = 1;
+
+/// An epoch based collector, the alternative to a hazard pointer Domain for read-heavy workloads.
+/// Loads made while pinned need no fence of their own, instead no retired object is reclaimed
+/// until every thread that was pinned when it got retired has unpinned again.
+pub struct Collector {
+    epoch: AtomicUsize,
+    locals: AtomicPtr<Local>,
+    garbage: [AtomicPtr<RetireLink>; GARBAGE_LISTS],
+    retired: AtomicUsize,
+}
+
+// A participant slot, reused the same way hazard slots are.
+struct Local {
+    // The epoch the slot is pinned in shifted left by one, or'ed with PINNED while it is pinned.
+    state: AtomicUsize,
+    next: AtomicPtr<Local>,
+    flag: AtomicBool,
+}
+
+// Guards never outlive the borrow of their collector, so by the time it is dropped nothing can be
+// pinned in it any more.
+impl Drop for Collector {
+    fn drop(&mut self) {
+        for garbage in &self.garbage {
+            unsafe { reclaim_list(garbage.swap(std::ptr::null_mut(), Ordering::AcqRel)) };
+        }
+        let mut current = self.locals.swap(std::ptr::null_mut(), Ordering::AcqRel);
+        while !current.is_null() {
+            let next = unsafe { (*current).next.load(Ordering::Acquire) };
+            let drop = unsafe { Box::from_raw(current) };
+            std::mem::drop(drop);
+            current = next;
+        }
+    }
+}
+
+impl Default for Collector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Collector {
+    #[cfg(not(loom))]
+    pub const fn new() -> Self {
+        Self {
+            epoch: AtomicUsize::new(0),
+            locals: AtomicPtr::new(std::ptr::null_mut()),
+            garbage: [const { AtomicPtr::new(std::ptr::null_mut()) }; GARBAGE_LISTS],
+            retired: AtomicUsize::new(0),
+        }
+    }
+
+    // The loom atomics can not be constructed in a const context.
+    #[cfg(loom)]
+    pub fn new() -> Self {
+        Self {
+            epoch: AtomicUsize::new(0),
+            locals: AtomicPtr::new(std::ptr::null_mut()),
+            garbage: std::array::from_fn(|_| AtomicPtr::new(std::ptr::null_mut())),
+            retired: AtomicUsize::new(0),
+        }
+    }
+
+    /// The collector used by everything that was not explicitly given another one.
+    pub fn global() -> &'static Collector {
+        &SHARED_COLLECTOR
+    }
+
+    /// The current global epoch.
+    pub fn epoch(&self) -> usize {
+        self.epoch.load(Ordering::SeqCst)
+    }
+
+    /// Pins the calling thread. Objects retired into this collector from now on are not reclaimed
+    /// before the returned guard is dropped. Pinning again while already pinned is cheap, the
+    /// nested guard shares the participant slot of the outer one.
+    pub fn pin(&self) -> Guard<'_> {
+        #[cfg(not(loom))]
+        if let Some(local) = PinCache::enter(self) {
+            return Guard {
+                collector: self,
+                local,
+                _marker: PhantomData,
+            };
+        }
+        let local = self.acquire();
+        let epoch = self.epoch.load(Ordering::SeqCst);
+        local.state.store(epoch << 1 | PINNED, Ordering::SeqCst);
+        // Every load the pinned thread makes from here on is ordered after the pin, so a thread
+        // advancing the epoch either sees the pin or the pinned thread sees the unlinking of
+        // whatever is about to be reclaimed.
+        fence(Ordering::SeqCst);
+        #[cfg(not(loom))]
+        PinCache::remember(self, local);
+        Guard {
+            collector: self,
+            local,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Tries to advance the epoch and, if that succeeds, reclaims everything that was retired two
+    /// epochs ago. Advancing fails while any thread is still pinned in an older epoch.
+    pub fn collect(&self) {
+        // The pin keeps the epoch from moving on by more than one until the garbage list has been
+        // taken, so nobody can be retiring into it in the meantime.
+        let guard = self.pin();
+        let epoch = guard.local.state.load(Ordering::Relaxed) >> 1;
+        if self.epoch.load(Ordering::SeqCst) != epoch {
+            return;
+        }
+        fence(Ordering::SeqCst);
+        let mut current = self.locals.load(Ordering::Acquire);
+        while !current.is_null() {
+            let state = unsafe { (*current).state.load(Ordering::SeqCst) };
+            if state & PINNED != 0 && state >> 1 != epoch {
+                return;
+            }
+            current = unsafe { (*current).next.load(Ordering::Acquire) };
+        }
+        if self
+            .epoch
+            .compare_exchange(epoch, epoch + 1, Ordering::SeqCst, Ordering::Relaxed)
+            .is_ok()
+        {
+            let garbage = &self.garbage[(epoch + 2) % GARBAGE_LISTS];
+            unsafe { reclaim_list(garbage.swap(std::ptr::null_mut(), Ordering::AcqRel)) };
+        }
+    }
+
+    /// Reclaims every object retired into this collector under the given cohort before returning,
+    /// see Domain::drain. A thread pinned in this collector keeps the epoch from advancing past
+    /// its own pin, so if the caller is one, nothing is waited for and false is returned. The
+    /// objects are then reclaimed by later collections as usual.
+    pub fn drain(&self, cohort: &Cohort) -> bool {
+        #[cfg(not(loom))]
+        if PinCache::pinned(self) {
+            self.collect();
+            return cohort.pending() == 0;
+        }
+        while cohort.pending() != 0 {
+            self.collect();
+            if cohort.pending() != 0 {
+                // Some thread is still pinned in an older epoch.
+                thread::yield_now();
+            }
+        }
+        true
+    }
+
+    // Must be called while pinned, which is what keeps the list from being reclaimed under it.
+    unsafe fn defer(&self, link: *mut RetireLink) {
+        // Whoever reads the epoch past this fence is also past the unlinking of the object.
+        fence(Ordering::SeqCst);
+        let epoch = self.epoch.load(Ordering::SeqCst);
+        let garbage = &self.garbage[epoch % GARBAGE_LISTS];
+        let mut current = garbage.load(Ordering::Acquire);
+        loop {
+            unsafe { (*link).next.store(current, Ordering::Relaxed) };
+            match garbage.compare_exchange(current, link, Ordering::AcqRel, Ordering::Acquire) {
+                Ok(_) => break,
+                Err(now) => current = now,
+            }
+        }
+        if self.retired.fetch_add(1, Ordering::Relaxed) % COLLECT_INTERVAL == COLLECT_INTERVAL - 1 {
+            self.collect();
+        }
+    }
+
+    fn acquire(&self) -> &Local {
+        let mut current = self.locals.load(Ordering::Acquire);
+        while !current.is_null() {
+            if unsafe { &(*current).flag }
+                .compare_exchange(true, false, Ordering::AcqRel, Ordering::Relaxed)
+                .is_ok()
+            {
+                return unsafe { &(*current) };
+            } else {
+                current = unsafe { (*current).next.load(Ordering::Acquire) };
+            }
+        }
+
+        let mut now = self.locals.load(Ordering::Acquire);
+        loop {
+            let new = Local {
+                state: AtomicUsize::new(0),
+                next: AtomicPtr::new(std::ptr::null_mut()),
+                flag: AtomicBool::new(false),
+            };
+            new.next.store(now, Ordering::Release);
+            let boxed = Box::into_raw(Box::new(new));
+            if self
+                .locals
+                .compare_exchange(now, boxed, Ordering::AcqRel, Ordering::Relaxed)
+                .is_ok()
+            {
+                return unsafe { &*boxed };
+            } else {
+                let drop = unsafe { Box::from_raw(boxed) };
+                std::mem::drop(drop);
+                now = self.locals.load(Ordering::Acquire);
+            }
+        }
+    }
+}
+
+/// SAFETY:
+///    None of the objects on the list may be reachable by any thread any more.
+unsafe fn reclaim_list(mut current: *mut RetireLink) {
+    while !current.is_null() {
+        // The link lives inside the object, so it has to be stepped over first.
+        let next = unsafe { (*current).next.load(Ordering::Acquire) };
+        unsafe { RetireLink::reclaim(current) };
+        current = next;
+    }
+}
+
+/// Pins the calling thread in the process wide collector.
+pub fn pin() -> Guard<'static> {
+    Collector::global().pin()
+}
+
+/// Keeps the thread pinned for as long as it is alive. Pointers loaded while pinned may be
+/// dereferenced until the guard is dropped, provided that whoever unlinks them retires them
+/// through the same collector.
+pub struct Guard<'c> {
+    collector: &'c Collector,
+    local: &'c Local,
+    // Nested pins are tracked per thread, so the guard has to stay on the one that pinned.
+    _marker: PhantomData<*const ()>,
+}
+
+impl Drop for Guard<'_> {
+    fn drop(&mut self) {
+        #[cfg(not(loom))]
+        if PinCache::leave(self.local) {
+            return;
+        }
+        self.local.state.store(0, Ordering::SeqCst);
+        self.local.flag.store(true, Ordering::Release);
+    }
+}
+
+impl<'c> Guard<'c> {
+    pub fn collector(&self) -> &'c Collector {
+        self.collector
+    }
+
+    /// Retires an unlinked object into the collector this guard pinned. It is reclaimed through
+    /// Retirable::reclaim once every thread that might still be reading it has unpinned.
+    ///
+    /// # Safety
+    ///   Same as Domain::retire.
+    pub unsafe fn defer_destroy<T: Retirable>(&self, ptr: *mut T) {
+        unsafe { self.collector.defer(RetireLink::attach(ptr, None)) };
+    }
+
+    /// Same as defer_destroy, but counts the object against the given cohort so that
+    /// Collector::drain can later wait for it to be reclaimed.
+    ///
+    /// # Safety
+    ///   Same as Domain::retire.
+    pub unsafe fn defer_destroy_in<T: Retirable>(&self, cohort: &Cohort, ptr: *mut T) {
+        unsafe { self.collector.defer(RetireLink::attach(ptr, Some(cohort))) };
+    }
+}
+
+// Being pinned already covers every load, there is nothing to publish.
+impl Shield for Guard<'_> {
+    fn protect<T>(&mut self, src: &AtomicPtr<T>) -> *mut T {
+        src.load(Ordering::Acquire)
+    }
+}
+
+impl Shields for Guard<'_> {
+    fn protect<T>(&mut self, _: usize, src: &AtomicPtr<T>) -> *mut T {
+        src.load(Ordering::Acquire)
+    }
+
+    fn swap(&mut self, _: usize, _: usize) {}
+}
+
+// Remembers the slots the thread is pinned through, one per collector, so that nested pins of the
+// same collector only bump a counter and drain can tell whether the thread is pinned in it. Loom
+// models threads on a single OS thread, so nesting is not shared there.
+#[cfg(not(loom))]
+thread_local! {
+    static PIN_CACHE: RefCell<Vec<PinCache>> = const { RefCell::new(Vec::new()) };
+}
+
+#[cfg(not(loom))]
+struct PinCache {
+    collector: *const Collector,
+    local: *const Local,
+    depth: usize,
+}
+
+#[cfg(not(loom))]
+impl PinCache {
+    fn enter(collector: &Collector) -> Option<&Local> {
+        PIN_CACHE
+            .try_with(|cache| {
+                let mut cache = cache.try_borrow_mut().ok()?;
+                let pin = cache
+                    .iter_mut()
+                    .find(|pin| std::ptr::eq(pin.collector, collector))?;
+                pin.depth += 1;
+                Some(unsafe { &*pin.local })
+            })
+            .ok()
+            .flatten()
+    }
+
+    fn remember(collector: &Collector, local: &Local) {
+        let _ = PIN_CACHE.try_with(|cache| {
+            if let Ok(mut cache) = cache.try_borrow_mut() {
+                cache.push(PinCache {
+                    collector,
+                    local,
+                    depth: 1,
+                });
+            }
+        });
+    }
+
+    fn pinned(collector: &Collector) -> bool {
+        PIN_CACHE
+            .try_with(|cache| {
+                cache.try_borrow().is_ok_and(|cache| {
+                    cache
+                        .iter()
+                        .any(|pin| std::ptr::eq(pin.collector, collector))
+                })
+            })
+            .unwrap_or(false)
+    }
+
+    /// Returns whether the slot is still pinned by another guard of this thread.
+    fn leave(local: &Local) -> bool {
+        PIN_CACHE
+            .try_with(|cache| {
+                let Ok(mut cache) = cache.try_borrow_mut() else {
+                    return false;
+                };
+                let Some(index) = cache.iter().position(|pin| std::ptr::eq(pin.local, local))
+                else {
+                    return false;
+                };
+                cache[index].depth -= 1;
+                if cache[index].depth == 0 {
+                    cache.swap_remove(index);
+                    return false;
+                }
+                true
+            })
+            .unwrap_or(false)
+    }
+}
+
+/// Lets a data structure retire its nodes through epochs, into either the process wide collector
+/// or a collector of its own, under a cohort so that they can be drained when it is dropped.
+pub struct EpochReclaimer {
+    collector: Option<Collector>,
+    cohort: Cohort,
+}
+
+impl Default for EpochReclaimer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EpochReclaimer {
+    pub fn new() -> Self {
+        Self {
+            collector: None,
+            cohort: Cohort::new(),
+        }
+    }
+
+    pub fn with_collector(collector: Collector) -> Self {
+        Self {
+            collector: Some(collector),
+            cohort: Cohort::new(),
+        }
+    }
+
+    pub fn collector(&self) -> &Collector {
+        self.collector
+            .as_ref()
+            .unwrap_or_else(|| Collector::global())
+    }
+}
+
+unsafe impl Reclaimer for EpochReclaimer {
+    type Shield<'a> = Guard<'a>;
+    type Shields<'a, const N: usize> = Guard<'a>;
+
+    fn shield(&self) -> Guard<'_> {
+        self.collector().pin()
+    }
+
+    fn shields<const N: usize>(&self) -> Guard<'_> {
+        self.collector().pin()
+    }
+
+    unsafe fn retire<T: Retirable>(&self, ptr: *mut T) {
+        let guard = self.collector().pin();
+        unsafe { guard.defer_destroy_in(&self.cohort, ptr) };
+    }
+
+    fn drain(&self) -> bool {
+        self.collector().drain(&self.cohort)
+    }
+}
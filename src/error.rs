@@ -0,0 +1,61 @@
+use std::fmt;
+
+/// Why a push failed. The value is handed back either way.
+#[derive(PartialEq, Eq)]
+pub enum PushError<T> {
+    /// A bounded structure had no room left.
+    Full(T),
+    /// Every attempt lost its compare and swap to another thread.
+    Contended(T),
+}
+
+impl<T> PushError<T> {
+    /// The value that could not be pushed.
+    pub fn into_inner(self) -> T {
+        match self {
+            PushError::Full(value) | PushError::Contended(value) => value,
+        }
+    }
+}
+
+// Written out so that the value does not have to be Debug, the same as for the channel errors of
+// std.
+impl<T> fmt::Debug for PushError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PushError::Full(_) => f.write_str("Full(..)"),
+            PushError::Contended(_) => f.write_str("Contended(..)"),
+        }
+    }
+}
+
+impl<T> fmt::Display for PushError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PushError::Full(_) => f.write_str("pushing onto a full structure"),
+            PushError::Contended(_) => f.write_str("pushing failed under contention, try again"),
+        }
+    }
+}
+
+impl<T> std::error::Error for PushError<T> {}
+
+/// Why a pop failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PopError {
+    /// There was nothing to pop.
+    Empty,
+    /// Every attempt lost its compare and swap to another thread.
+    Contended,
+}
+
+impl fmt::Display for PopError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PopError::Empty => f.write_str("popping from an empty structure"),
+            PopError::Contended => f.write_str("popping failed under contention, try again"),
+        }
+    }
+}
+
+impl std::error::Error for PopError {}
@@ -0,0 +1,106 @@
+#![allow(unexpected_cfgs)]
+
+// Protecting a pointer is a store to the hazard followed by a load validating the source. Without
+// a store-load fence in between, the store may become visible only after the load, and a scanner
+// can then miss the hazard and reclaim a pointer that the reader is about to use. The default
+// build relies on the Release store and Acquire load only, which does not rule that out on any
+// architecture: even x86 lets a store be delayed past a later load from another location. Builds
+// that reclaim while other threads read should enable one of the features below.
+//
+//   seqcst-fence:     a SeqCst fence on both sides.
+//   asymmetric-fence: readers only keep the compiler from reordering, and the scanner forces a
+//                     full barrier onto every running thread of the process through
+//                     membarrier(MEMBARRIER_CMD_PRIVATE_EXPEDITED). Where that can not be
+//                     registered it falls back to SeqCst fences. Once registered, readers rely on
+//                     the barrier, so a failing one is fatal rather than a reason to fall back.
+
+#[cfg(all(loom, any(feature = "seqcst-fence", feature = "asymmetric-fence")))]
+pub(crate) fn reader() {
+    loom::sync::atomic::fence(std::sync::atomic::Ordering::SeqCst);
+}
+
+#[cfg(all(loom, any(feature = "seqcst-fence", feature = "asymmetric-fence")))]
+pub(crate) fn scanner() {
+    loom::sync::atomic::fence(std::sync::atomic::Ordering::SeqCst);
+}
+
+#[cfg(all(not(loom), feature = "seqcst-fence"))]
+pub(crate) fn reader() {
+    std::sync::atomic::fence(std::sync::atomic::Ordering::SeqCst);
+}
+
+#[cfg(all(not(loom), feature = "seqcst-fence"))]
+pub(crate) fn scanner() {
+    std::sync::atomic::fence(std::sync::atomic::Ordering::SeqCst);
+}
+
+#[cfg(all(not(loom), feature = "asymmetric-fence", not(feature = "seqcst-fence")))]
+pub(crate) fn reader() {
+    if membarrier::registered() {
+        std::sync::atomic::compiler_fence(std::sync::atomic::Ordering::SeqCst);
+    } else {
+        std::sync::atomic::fence(std::sync::atomic::Ordering::SeqCst);
+    }
+}
+
+#[cfg(all(not(loom), feature = "asymmetric-fence", not(feature = "seqcst-fence")))]
+pub(crate) fn scanner() {
+    if !membarrier::registered() {
+        std::sync::atomic::fence(std::sync::atomic::Ordering::SeqCst);
+    } else if !membarrier::barrier() {
+        // A fence on this side alone does not order the hazard store of a reader before its
+        // validating load, and readers no longer fence themselves. Panicking before the hazards are
+        // read leaves the retired pointers unreclaimed, which is safe.
+        panic!("membarrier failed after registration, hazards can no longer be read safely");
+    }
+}
+
+#[cfg(not(any(feature = "seqcst-fence", feature = "asymmetric-fence")))]
+pub(crate) fn reader() {}
+
+#[cfg(not(any(feature = "seqcst-fence", feature = "asymmetric-fence")))]
+pub(crate) fn scanner() {}
+
+#[cfg(all(not(loom), feature = "asymmetric-fence", not(feature = "seqcst-fence")))]
+mod membarrier {
+    use std::sync::OnceLock;
+
+    // Readers and scanners both go through the same OnceLock, so they always agree on whether the
+    // light reader side may be used.
+    static REGISTERED: OnceLock<bool> = OnceLock::new();
+
+    #[cfg(target_os = "linux")]
+    const MEMBARRIER_CMD_PRIVATE_EXPEDITED: libc::c_int = 1 << 3;
+    #[cfg(target_os = "linux")]
+    const MEMBARRIER_CMD_REGISTER_PRIVATE_EXPEDITED: libc::c_int = 1 << 4;
+
+    pub(super) fn registered() -> bool {
+        *REGISTERED.get_or_init(register)
+    }
+
+    #[cfg(target_os = "linux")]
+    fn register() -> bool {
+        unsafe {
+            libc::syscall(
+                libc::SYS_membarrier,
+                MEMBARRIER_CMD_REGISTER_PRIVATE_EXPEDITED,
+                0,
+            ) == 0
+        }
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn register() -> bool {
+        false
+    }
+
+    #[cfg(target_os = "linux")]
+    pub(super) fn barrier() -> bool {
+        unsafe { libc::syscall(libc::SYS_membarrier, MEMBARRIER_CMD_PRIVATE_EXPEDITED, 0) == 0 }
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub(super) fn barrier() -> bool {
+        false
+    }
+}
@@ -0,0 +1,445 @@
+use crate::hazard::{BoxedPointer, Doer, DoerWrapper, Domain, HazardReclaimer, Holder};
+use crate::list::{CURRENT, NEXT, PREVIOUS, mark, marked, unmarked};
+use crate::reclaim::Reclaimer;
+use crate::sync::atomic::{AtomicPtr, AtomicUsize};
+use std::hash::{BuildHasher, Hash, RandomState};
+use std::marker::PhantomData;
+use std::ops::Deref;
+use std::sync::atomic::Ordering;
+
+// Bucket b lives in segment 0 for b < 2 and in segment log2(b) otherwise, so segment s > 0 holds
+// 2^s buckets and the table can keep doubling without ever moving a bucket.
+const SEGMENTS: usize = usize::BITS as usize;
+
+// The table doubles once there are more than this many entries per bucket on average.
+const LOAD_FACTOR: usize = 2;
+
+const HIGH_BIT: usize = 1 << (usize::BITS - 1);
+
+static BOXED: BoxedPointer = BoxedPointer::new();
+
+// Split ordering sorts the list by bit reversed hashes. Regular keys get the high bit set before
+// reversing and bucket sentinels do not, so a sentinel always sorts before the keys of its bucket.
+fn regular_key(hash: usize) -> usize {
+    (hash | HIGH_BIT).reverse_bits()
+}
+
+fn sentinel_key(bucket: usize) -> usize {
+    bucket.reverse_bits()
+}
+
+fn segment_of(bucket: usize) -> (usize, usize) {
+    if bucket < 2 {
+        (0, bucket)
+    } else {
+        let segment = (usize::BITS - 1 - bucket.leading_zeros()) as usize;
+        (segment, bucket - (1 << segment))
+    }
+}
+
+fn segment_len(segment: usize) -> usize {
+    if segment == 0 { 2 } else { 1 << segment }
+}
+
+struct Node<K, V> {
+    split_key: usize,
+    // None for bucket sentinels.
+    entry: Option<(K, V)>,
+    next: AtomicPtr<Node<K, V>>,
+}
+
+impl<K, V> Node<K, V> {
+    fn new(split_key: usize, entry: Option<(K, V)>) -> *mut Self {
+        Box::into_raw(Box::new(Self {
+            split_key,
+            entry,
+            next: AtomicPtr::new(std::ptr::null_mut()),
+        }))
+    }
+}
+
+struct Position<K, V> {
+    found: bool,
+    previous: *const AtomicPtr<Node<K, V>>,
+    current: *mut Node<K, V>,
+}
+
+// Protects the pointer loaded from src with the holder until the holder loads the next one.
+fn protect<K, V>(holder: &mut Holder<'_>, src: &AtomicPtr<Node<K, V>>) -> *mut Node<K, V> {
+    unsafe { holder.load_pointer(src) }.map_or(std::ptr::null_mut(), |guard| guard.into_raw())
+}
+
+/// A value of a HashMap, kept from being reclaimed by the holder its node was found with.
+pub struct Guard<'d, V> {
+    value: *const V,
+    _holder: Holder<'d>,
+}
+
+impl<V> Deref for Guard<'_, V> {
+    type Target = V;
+    fn deref(&self) -> &V {
+        unsafe { &*self.value }
+    }
+}
+
+/// A lock-free hash map after Shalev and Shavit's split-ordered lists. All entries live in a
+/// single Harris-Michael list sorted by their bit reversed hash, and every bucket is a shortcut
+/// into it through a sentinel node. Doubling the number of buckets therefore never moves an
+/// entry, new buckets are initialized lazily by splicing their sentinel into the list.
+///
+/// Readers protect every node they step on with Holder::load_pointer, handing three holders over
+/// as they go, and removed nodes are retired through DoerWrapper::retire under the cohort of the
+/// map, so that dropping the map reclaims them.
+pub struct HashMap<K, V, S = RandomState> {
+    segments: [AtomicPtr<AtomicPtr<Node<K, V>>>; SEGMENTS],
+    buckets: AtomicUsize,
+    len: AtomicUsize,
+    hasher: S,
+    reclaimer: HazardReclaimer,
+    marker: PhantomData<Node<K, V>>,
+}
+
+unsafe impl<K, V, S> Send for HashMap<K, V, S>
+where
+    K: Send + Sync,
+    V: Send + Sync,
+    S: Send,
+{
+}
+unsafe impl<K, V, S> Sync for HashMap<K, V, S>
+where
+    K: Send + Sync,
+    V: Send + Sync,
+    S: Sync,
+{
+}
+
+impl<K, V, S> Drop for HashMap<K, V, S> {
+    fn drop(&mut self) {
+        // Every node, sentinels included, is reachable from the sentinel of bucket 0.
+        let first = self.segments[0].load(Ordering::Acquire);
+        let mut current = unsafe { (*first).load(Ordering::Acquire) };
+        while !current.is_null() {
+            let owned = unsafe { Box::from_raw(current) };
+            current = unmarked(owned.next.load(Ordering::Acquire));
+        }
+        for (segment, buckets) in self.segments.iter().enumerate() {
+            let buckets = buckets.load(Ordering::Acquire);
+            if !buckets.is_null() {
+                let slice = std::ptr::slice_from_raw_parts_mut(buckets, segment_len(segment));
+                let owned = unsafe { Box::from_raw(slice) };
+                std::mem::drop(owned);
+            }
+        }
+        self.reclaimer.drain();
+    }
+}
+
+impl<K: Hash + Eq, V> Default for HashMap<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Hash + Eq, V> HashMap<K, V> {
+    pub fn new() -> Self {
+        Self::with_hasher(RandomState::new())
+    }
+
+    /// Creates a map that retires its nodes into a domain of its own instead of the process wide
+    /// one.
+    pub fn with_domain(domain: Domain) -> Self {
+        Self::with(RandomState::new(), HazardReclaimer::with_domain(domain))
+    }
+}
+
+impl<K, V, S> HashMap<K, V, S> {
+    pub fn domain(&self) -> &Domain {
+        self.reclaimer.domain()
+    }
+
+    pub fn len(&self) -> usize {
+        self.len.load(Ordering::Relaxed)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<K: Hash + Eq, V, S: BuildHasher> HashMap<K, V, S> {
+    pub fn with_hasher(hasher: S) -> Self {
+        Self::with(hasher, HazardReclaimer::new())
+    }
+
+    fn with(hasher: S, reclaimer: HazardReclaimer) -> Self {
+        let map = Self {
+            segments: std::array::from_fn(|_| AtomicPtr::new(std::ptr::null_mut())),
+            buckets: AtomicUsize::new(2),
+            len: AtomicUsize::new(0),
+            hasher,
+            reclaimer,
+            marker: PhantomData,
+        };
+        // Bucket 0 is the head of the whole list, every other bucket is split off from it.
+        let slot = map.slot(0);
+        slot.store(Node::new(sentinel_key(0), None), Ordering::Release);
+        map
+    }
+
+    /// Inserts the pair unless the key is already present, in which case the pair is dropped and
+    /// false is returned.
+    pub fn insert(&self, key: K, value: V) -> bool {
+        let hash = self.hasher.hash_one(&key) as usize;
+        let node = Node::new(regular_key(hash), Some((key, value)));
+        let sentinel = self.sentinel(hash);
+        let mut holders = self.holders();
+        if !self.link(sentinel, node, &mut holders) {
+            let owned = unsafe { Box::from_raw(node) };
+            std::mem::drop(owned);
+            return false;
+        }
+        let len = self.len.fetch_add(1, Ordering::Relaxed) + 1;
+        let buckets = self.buckets.load(Ordering::Relaxed);
+        if len > buckets * LOAD_FACTOR && buckets < HIGH_BIT {
+            let _ = self.buckets.compare_exchange(
+                buckets,
+                buckets * 2,
+                Ordering::AcqRel,
+                Ordering::Relaxed,
+            );
+        }
+        true
+    }
+
+    /// Removes the key, returning whether it was present.
+    pub fn remove(&self, key: &K) -> bool {
+        let hash = self.hasher.hash_one(key) as usize;
+        let split_key = regular_key(hash);
+        let sentinel = self.sentinel(hash);
+        let mut holders = self.holders();
+        loop {
+            let position = self.find(sentinel, split_key, Some(key), &mut holders);
+            if !position.found {
+                return false;
+            }
+            let current = position.current;
+            let next = unsafe { (*current).next.load(Ordering::Acquire) };
+            if marked(next) {
+                continue;
+            }
+            if unsafe { &(*current).next }
+                .compare_exchange(next, mark(next), Ordering::AcqRel, Ordering::Relaxed)
+                .is_err()
+            {
+                continue;
+            }
+            self.len.fetch_sub(1, Ordering::Relaxed);
+            if unsafe { &*position.previous }
+                .compare_exchange(current, next, Ordering::AcqRel, Ordering::Relaxed)
+                .is_ok()
+            {
+                unsafe { self.retire(current) };
+            } else {
+                self.find(sentinel, split_key, Some(key), &mut holders);
+            }
+            return true;
+        }
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        let hash = self.hasher.hash_one(key) as usize;
+        let sentinel = self.sentinel(hash);
+        let mut holders = self.holders();
+        self.find(sentinel, regular_key(hash), Some(key), &mut holders)
+            .found
+    }
+
+    /// Returns the value stored under the key, protected for as long as the guard is alive even
+    /// if the key gets removed in the meantime.
+    pub fn get(&self, key: &K) -> Option<Guard<'_, V>> {
+        let hash = self.hasher.hash_one(key) as usize;
+        let sentinel = self.sentinel(hash);
+        let mut holders = self.holders();
+        let position = self.find(sentinel, regular_key(hash), Some(key), &mut holders);
+        if !position.found {
+            return None;
+        }
+        let (_, value) = unsafe { (*position.current).entry.as_ref() }
+            .expect("Only sentinels have no entry and they are never looked up by key");
+        let [_, holder, _] = holders;
+        Some(Guard {
+            value,
+            _holder: holder,
+        })
+    }
+
+    fn holders(&self) -> [Holder<'_>; 3] {
+        std::array::from_fn(|_| Holder::new_in(self.domain()))
+    }
+
+    // # Safety
+    //   The node must have been unlinked from the list by the caller and nobody else.
+    unsafe fn retire(&self, node: *mut Node<K, V>) {
+        let domain = self.domain();
+        unsafe { DoerWrapper::unlinked(node, domain, self.reclaimer.cohort(), &BOXED) }.retire();
+    }
+
+    // Sentinels are never removed, so the pointer stays valid for as long as the map does.
+    fn sentinel(&self, hash: usize) -> *mut Node<K, V> {
+        let bucket = hash & (self.buckets.load(Ordering::Acquire) - 1);
+        self.bucket(bucket)
+    }
+
+    fn bucket(&self, bucket: usize) -> *mut Node<K, V> {
+        let slot = self.slot(bucket);
+        let sentinel = slot.load(Ordering::Acquire);
+        if !sentinel.is_null() {
+            return sentinel;
+        }
+        // A bucket is split off from the one that is equal to it without its highest bit.
+        let parent = bucket & !(1 << (usize::BITS - 1 - bucket.leading_zeros()));
+        let parent = self.bucket(parent);
+        let node = Node::new(sentinel_key(bucket), None);
+        let mut holders = self.holders();
+        let sentinel = if self.link(parent, node, &mut holders) {
+            node
+        } else {
+            // Another thread initialized the bucket first, its sentinel is the one in the list.
+            let owned = unsafe { Box::from_raw(node) };
+            std::mem::drop(owned);
+            let position = self.find(parent, sentinel_key(bucket), None, &mut holders);
+            position.current
+        };
+        slot.store(sentinel, Ordering::Release);
+        sentinel
+    }
+
+    fn slot(&self, bucket: usize) -> &AtomicPtr<Node<K, V>> {
+        let (segment, offset) = segment_of(bucket);
+        let mut buckets = self.segments[segment].load(Ordering::Acquire);
+        if buckets.is_null() {
+            let fresh: Box<[AtomicPtr<Node<K, V>>]> = (0..segment_len(segment))
+                .map(|_| AtomicPtr::new(std::ptr::null_mut()))
+                .collect();
+            let fresh = Box::into_raw(fresh) as *mut AtomicPtr<Node<K, V>>;
+            buckets = match self.segments[segment].compare_exchange(
+                std::ptr::null_mut(),
+                fresh,
+                Ordering::AcqRel,
+                Ordering::Acquire,
+            ) {
+                Ok(_) => fresh,
+                Err(current) => {
+                    let slice = std::ptr::slice_from_raw_parts_mut(fresh, segment_len(segment));
+                    let owned = unsafe { Box::from_raw(slice) };
+                    std::mem::drop(owned);
+                    current
+                }
+            };
+        }
+        unsafe { &*buckets.add(offset) }
+    }
+
+    // Links the node into the list after start unless an equal node is already there.
+    fn link(
+        &self,
+        start: *mut Node<K, V>,
+        node: *mut Node<K, V>,
+        holders: &mut [Holder<'_>; 3],
+    ) -> bool {
+        let split_key = unsafe { (*node).split_key };
+        let key = unsafe { (*node).entry.as_ref() }.map(|(key, _)| key);
+        loop {
+            let position = self.find(start, split_key, key, holders);
+            if position.found {
+                return false;
+            }
+            unsafe { (*node).next.store(position.current, Ordering::Relaxed) };
+            if unsafe { &*position.previous }
+                .compare_exchange(position.current, node, Ordering::AcqRel, Ordering::Relaxed)
+                .is_ok()
+            {
+                return true;
+            }
+        }
+    }
+
+    // Searches the list from the sentinel start on, the same way List::find does. Keys with equal
+    // split keys are not ordered among each other, so all of them are compared.
+    fn find(
+        &self,
+        start: *mut Node<K, V>,
+        split_key: usize,
+        key: Option<&K>,
+        holders: &mut [Holder<'_>; 3],
+    ) -> Position<K, V> {
+        'retry: loop {
+            // Sentinels are never marked nor reclaimed, so start needs no protection.
+            let mut previous: *const AtomicPtr<Node<K, V>> = unsafe { &(*start).next };
+            let mut current = protect(&mut holders[CURRENT], unsafe { &*previous });
+            loop {
+                // Only after unlinking a node, when the node before it was removed as well.
+                if marked(current) {
+                    continue 'retry;
+                }
+                if current.is_null() {
+                    return Position {
+                        found: false,
+                        previous,
+                        current,
+                    };
+                }
+                let next = protect(&mut holders[NEXT], unsafe { &(*current).next });
+                if unsafe { &*previous }.load(Ordering::Acquire) != current {
+                    continue 'retry;
+                }
+                if marked(next) {
+                    // The holder protects the marked pointer and thereby nothing, so next is
+                    // loaded again from previous once current has been unlinked.
+                    if unsafe { &*previous }
+                        .compare_exchange(
+                            current,
+                            unmarked(next),
+                            Ordering::AcqRel,
+                            Ordering::Relaxed,
+                        )
+                        .is_err()
+                    {
+                        continue 'retry;
+                    }
+                    unsafe { self.retire(current) };
+                    current = protect(&mut holders[CURRENT], unsafe { &*previous });
+                    continue;
+                }
+                let current_key = unsafe { (*current).split_key };
+                if current_key > split_key {
+                    return Position {
+                        found: false,
+                        previous,
+                        current,
+                    };
+                }
+                if current_key == split_key {
+                    let entry = unsafe { (*current).entry.as_ref() };
+                    let matches = match (key, entry) {
+                        (Some(key), Some((current, _))) => key == current,
+                        (None, None) => true,
+                        _ => false,
+                    };
+                    if matches {
+                        return Position {
+                            found: true,
+                            previous,
+                            current,
+                        };
+                    }
+                }
+                previous = unsafe { &(*current).next };
+                holders.swap(CURRENT, PREVIOUS);
+                holders.swap(NEXT, CURRENT);
+                current = next;
+            }
+        }
+    }
+}
@@ -1,60 +1,134 @@
 #![allow(unexpected_cfgs)]
 
-use crate::sync::atomic::{AtomicBool, AtomicPtr};
-use std::collections::HashSet;
+use crate::fence;
+use crate::reclaim::{Reclaimer, Shield, Shields};
+use crate::sync::atomic::{AtomicBool, AtomicPtr, AtomicUsize};
+use crate::sync::thread;
+use std::cell::{RefCell, UnsafeCell};
 use std::convert::AsRef;
 use std::marker::PhantomData;
-use std::ops::{Deref, DerefMut};
+use std::ops::Deref;
 use std::ptr::NonNull;
 use std::sync::atomic::Ordering;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread::Thread;
+use std::time::{Duration, Instant};
 
 #[cfg(loom)]
 loom::lazy_static! {
-    static ref SHARED_DOMAIN: GlobalDomain = GlobalDomain {
-            list: HazardList {
-                head: AtomicPtr::new(std::ptr::null_mut()),
-            },
-            ret: RetiredList {
-                head: AtomicPtr::new(std::ptr::null_mut()),
-            }
-    };
+    static ref SHARED_DOMAIN: Domain = Domain::new();
 }
 
 #[cfg(not(loom))]
-pub(crate) static SHARED_DOMAIN: GlobalDomain = GlobalDomain {
-    list: HazardList {
-        head: AtomicPtr::new(std::ptr::null_mut()),
-    },
-    ret: RetiredList {
-        head: AtomicPtr::new(std::ptr::null_mut()),
-    },
-};
+pub(crate) static SHARED_DOMAIN: Domain = Domain::new();
 
-#[derive(Default)]
-pub struct Holder(Option<&'static Hazard>);
+thread_local! {
+    static SCAN_BUFFER: RefCell<Vec<*mut ()>> = const { RefCell::new(Vec::new()) };
+}
+
+/// A Holder owns at most one hazard slot of the domain it is bound to. Holders created through
+/// Default are bound to the process wide domain.
+pub struct Holder<'d> {
+    hazard: Option<&'d Hazard>,
+    domain: &'d Domain,
+}
 
+impl Default for Holder<'static> {
+    fn default() -> Self {
+        Self::new_in(Domain::global())
+    }
+}
+
+impl Drop for Holder<'_> {
+    fn drop(&mut self) {
+        if let Some(hazard) = self.hazard.take() {
+            hazard.protect(std::ptr::null_mut());
+            self.domain.release(hazard);
+        }
+    }
+}
+
+/// A Guard borrows the hazard slot of the Holder it was loaded through. Dropping it only clears
+/// the protection, the slot itself goes back to the domain when the Holder is dropped.
+///
+/// References handed out by a Guard borrow the guard itself, so they can not outlive the
+/// protection:
+///
+/// ```compile_fail
+/// use electron::Holder;
+/// use electron::sync::atomic::AtomicPtr;
+///
+/// let atomic = AtomicPtr::new(Box::into_raw(Box::new(5)));
+/// let mut holder = Holder::default();
+/// let reference = {
+///     let guard = unsafe { holder.load_pointer(&atomic) }.unwrap();
+///     guard.as_ref()
+/// };
+/// assert_eq!(*reference, 5);
+/// ```
+///
+/// Nor can the guard outlive the Holder whose slot it borrows:
+///
+/// ```compile_fail
+/// use electron::Holder;
+/// use electron::sync::atomic::AtomicPtr;
+///
+/// let atomic = AtomicPtr::new(Box::into_raw(Box::new(5)));
+/// let guard = {
+///     let mut holder = Holder::default();
+///     unsafe { holder.load_pointer(&atomic) }.unwrap()
+/// };
+/// assert_eq!(*guard, 5);
+/// ```
+///
+/// Other threads may be reading the same value, so a Guard does not hand out mutable references
+/// through DerefMut:
+///
+/// ```compile_fail
+/// use electron::Holder;
+/// use electron::sync::atomic::AtomicPtr;
+///
+/// let atomic = AtomicPtr::new(Box::into_raw(Box::new(5)));
+/// let mut holder = Holder::default();
+/// let mut guard = unsafe { holder.load_pointer(&atomic) }.unwrap();
+/// *guard = 7;
+/// ```
 pub struct Guard<'a, T> {
-    hazptr: &'static Hazard,
-    pub(crate) data: *mut T,
+    hazptr: &'a Hazard,
+    data: *mut T,
     _marker: PhantomData<&'a T>,
 }
 
-/// SAFETY:
-///    The caller must make sure that the reference is not used
-///    after the underlying thing has been deallocated as the
-///    lifetime of the reference given out in this case is
-///    unbounded.
+impl<T> Guard<'_, T> {
+    /// The protected pointer. It stays safe to dereference for as long as the guard is alive.
+    pub fn as_ptr(&self) -> *mut T {
+        self.data
+    }
+
+    /// # Safety
+    ///   Other threads may hold guards to the same value at the same time. The caller must make
+    ///   sure that nobody else reads or writes the value while the returned reference is alive.
+    pub unsafe fn as_mut(&mut self) -> &mut T {
+        unsafe { &mut (*self.data) }
+    }
+
+    /// Gives up the guard but not the protection, which lasts until the holder loads another
+    /// pointer or is dropped.
+    pub(crate) fn into_raw(self) -> *mut T {
+        let data = self.data;
+        std::mem::forget(self);
+        data
+    }
+}
+
+// The reference is bounded by the borrow of the guard, which in turn can not outlive the hazard
+// keeping the value from being reclaimed.
 impl<T> AsRef<T> for Guard<'_, T> {
     fn as_ref(&self) -> &T {
         unsafe { &(*self.data) }
     }
 }
 
-/// SAFETY:
-///    The caller must make sure that the reference is not used
-///    after the underlying thing has been deallocated as the
-///    lifetime of the reference given out in this case is
-///    unbounded.
 impl<T> Deref for Guard<'_, T> {
     type Target = T;
     fn deref(&self) -> &Self::Target {
@@ -62,120 +136,321 @@ impl<T> Deref for Guard<'_, T> {
     }
 }
 
-/// SAFETY:
-///  This method can cause safety issues so it must be handled with care.
-///  If two threads deref_mut the guard to the same underlying T we will
-///  then have two mutable pointers to the same thing. If they are used to
-///  read or write at the same time, we will run into undefined behaviour.
-///  Further the lifetime of the mutable reference given out is unbounded.
-///  So it must be handled with care.
-impl<T> DerefMut for Guard<'_, T> {
-    fn deref_mut(&mut self) -> &mut Self::Target {
-        unsafe { &mut (*self.data) }
-    }
-}
-
 impl<T> Drop for Guard<'_, T> {
     fn drop(&mut self) {
-        self.hazptr
-            .ptr
-            .store(std::ptr::null_mut(), Ordering::Release);
-        self.hazptr.flag.store(true, Ordering::Release);
+        self.hazptr.protect(std::ptr::null_mut());
     }
 }
 
-impl Holder {
-    /// SAFETY:
+impl<'d> Holder<'d> {
+    /// Creates a holder whose hazard slot will be taken from the given domain. Pointers loaded
+    /// through it are only protected against reclamation in that same domain.
+    pub fn new_in(domain: &'d Domain) -> Self {
+        Self {
+            hazard: None,
+            domain,
+        }
+    }
+
+    pub fn domain(&self) -> &'d Domain {
+        self.domain
+    }
+
+    /// # Safety
     ///   1. The user must pass a valid pointer. Passing in invalid pointers such as a misaligned
     ///      one will cause undefined behaviour.
     ///   2. If a null pointer is passed that will be taken care of by the implementation as we
     ///      have made sure using NonNull that it does not get dereferenced.
     pub unsafe fn load_pointer<'a, T>(&'a mut self, ptr: &'_ AtomicPtr<T>) -> Option<Guard<'a, T>> {
-        let hazptr = if let Some(t) = self.0 {
+        let (hazptr, data) = self.publish(ptr);
+        if NonNull::new(data).is_some() {
+            Some(Guard {
+                hazptr,
+                data,
+                _marker: PhantomData,
+            })
+        } else {
+            None
+        }
+    }
+
+    fn publish<T>(&mut self, ptr: &AtomicPtr<T>) -> (&'d Hazard, *mut T) {
+        let hazptr = if let Some(t) = self.hazard {
             t
         } else {
-            let ptr = SHARED_DOMAIN.acquire();
-            self.0 = Some(ptr);
+            let ptr = self.domain.acquire();
+            self.hazard = Some(ptr);
             ptr
         };
-        let mut ptr1 = ptr.load(Ordering::Acquire);
-        let ret = loop {
-            hazptr.protect(ptr1 as *mut ());
-            let ptr2 = ptr.load(Ordering::Acquire);
-            if ptr1 == ptr2 {
-                if NonNull::new(ptr1).is_some() {
-                    let data = ptr1;
-                    break Some(Guard {
-                        hazptr: &hazptr,
-                        data: data,
-                        _marker: PhantomData,
-                    });
-                } else {
-                    break None;
-                }
-            } else {
-                ptr1 = ptr2;
-            }
-        };
-        return ret;
+        (hazptr, hazptr.publish(ptr))
     }
 
-    ///SAFETY:
+    /// # Safety
     ///  1. Swap ensures that the old pointer gets retired. The user must make sure that similar to
     ///     the load method, a valid pointer is passed failing which will cause undefined
     ///     behaviour.
     ///  2. Calling the swap method with a retired pointer will cause the retired pointer to be
     ///     retired again which will lead to it being double reclaimed leading to undefined
     ///     behaviour. The user must ensure that this does not happen.
-    pub unsafe fn swap<T>(
+    ///  3. The old pointer is retired into the domain this holder is bound to, so every reader of
+    ///     the atomic must be protecting its loads through that same domain.
+    ///  4. The deleter is run on whichever thread reclaims the pointer. It can be one of the zero
+    ///     sized deleters below or any `FnOnce(*mut T) + Send` closure, e.g. one handing the
+    ///     pointer back to an object pool.
+    pub unsafe fn swap<T, D: Deleter<T>>(
         &mut self,
         atomic: &'_ AtomicPtr<T>,
         ptr: *mut T,
-        deleter: &'static dyn Deleter,
-    ) -> Option<DoerWrapper<'_, T>> {
+        deleter: D,
+    ) -> Option<DoerWrapper<'d, T, D>> {
         let current = atomic.swap(ptr, Ordering::AcqRel);
         if current.is_null() {
-            return None;
+            None
         } else {
-            let wrapper = DoerWrapper {
+            Some(DoerWrapper {
                 inner: current,
-                domain: &SHARED_DOMAIN,
-                deleter: deleter,
-            };
-            return Some(wrapper);
+                domain: self.domain,
+                cohort: None,
+                deleter: Some(deleter),
+            })
         }
     }
 
-    ///SAFETY:
+    /// # Safety
     ///  1. This method provides a way to get the wrapper to call the retire method if the user is
     ///     not relying on swap. It must be used with care as repeatedly using load without
     ///     using this method and calling retire on it will lead to memory leaks.
-    pub unsafe fn get_wrapper<T>(
+    ///  2. Same as for swap, the deleter may be any `FnOnce(*mut T) + Send` closure.
+    pub unsafe fn get_wrapper<T, D: Deleter<T>>(
         &mut self,
         atomic: &'_ AtomicPtr<T>,
-        deleter: &'static dyn Deleter,
-    ) -> Option<DoerWrapper<'_, T>> {
+        deleter: D,
+    ) -> Option<DoerWrapper<'d, T, D>> {
         let current = atomic.swap(std::ptr::null_mut(), Ordering::AcqRel);
         if current.is_null() {
-            return None;
+            None
         } else {
-            let wrapper = DoerWrapper {
+            Some(DoerWrapper {
                 inner: current,
-                domain: &SHARED_DOMAIN,
-                deleter: deleter,
-            };
-            return Some(wrapper);
+                domain: self.domain,
+                cohort: None,
+                deleter: Some(deleter),
+            })
         }
     }
 
-    fn get_domain() -> &'static GlobalDomain {
-        &SHARED_DOMAIN
+    /// The counters of the process wide domain, see Domain::stats.
+    pub fn stats() -> DomainStats {
+        Domain::global().stats()
     }
 
+    /// Runs a reclamation pass over the process wide domain.
     pub fn try_reclaim() {
-        let domain = Self::get_domain();
-        unsafe {
-            domain.ret.reclaim(&domain.list);
+        Domain::global().try_reclaim();
+    }
+
+    /// Tears down the process wide domain, see Domain::shutdown.
+    ///
+    /// # Safety
+    ///   Same as Domain::shutdown.
+    pub unsafe fn reclaim_all() {
+        unsafe { Domain::global().shutdown() };
+    }
+}
+
+impl Shield for Holder<'_> {
+    fn protect<T>(&mut self, src: &AtomicPtr<T>) -> *mut T {
+        self.publish(src).1
+    }
+}
+
+/// N hazard slots of one domain, acquired together. Traversals that have to keep a few nodes
+/// protected at once, such as hand-over-hand over a linked list, protect into one slot and then
+/// swap slots around instead of creating a Holder per pointer.
+pub struct HolderArray<'d, const N: usize> {
+    hazards: [&'d Hazard; N],
+    domain: &'d Domain,
+}
+
+impl<const N: usize> Default for HolderArray<'static, N> {
+    fn default() -> Self {
+        Self::new_in(Domain::global())
+    }
+}
+
+impl<const N: usize> Drop for HolderArray<'_, N> {
+    fn drop(&mut self) {
+        for hazard in self.hazards {
+            hazard.protect(std::ptr::null_mut());
+            self.domain.release(hazard);
+        }
+    }
+}
+
+impl<'d, const N: usize> HolderArray<'d, N> {
+    pub fn new_in(domain: &'d Domain) -> Self {
+        Self {
+            hazards: domain.acquire_many(),
+            domain,
+        }
+    }
+
+    pub fn domain(&self) -> &'d Domain {
+        self.domain
+    }
+
+    /// Loads the pointer stored in src and protects it through slot index, replacing whatever the
+    /// slot protected before. The pointer stays safe to dereference until the slot is reset,
+    /// protects something else or the array is dropped.
+    pub fn protect<T>(&mut self, index: usize, src: &AtomicPtr<T>) -> *mut T {
+        self.hazards[index].publish(src)
+    }
+
+    /// Same as protect for atomics whose values carry tag bits, such as the mark of a logically
+    /// deleted node. The slot protects strip(value), while the untouched value is returned.
+    pub fn protect_with<T>(
+        &mut self,
+        index: usize,
+        src: &AtomicPtr<T>,
+        strip: impl Fn(*mut T) -> *mut T,
+    ) -> *mut T {
+        self.hazards[index].publish_with(src, strip)
+    }
+
+    /// Exchanges the protections of two slots. Both pointers stay protected throughout, only the
+    /// index they are protected under changes.
+    pub fn swap(&mut self, i: usize, j: usize) {
+        self.hazards.swap(i, j);
+    }
+
+    /// Stops protecting whatever slot index protected.
+    pub fn reset(&mut self, index: usize) {
+        self.hazards[index].protect(std::ptr::null_mut());
+    }
+}
+
+impl<const N: usize> Shields for HolderArray<'_, N> {
+    fn protect<T>(&mut self, index: usize, src: &AtomicPtr<T>) -> *mut T {
+        HolderArray::protect(self, index, src)
+    }
+
+    fn swap(&mut self, i: usize, j: usize) {
+        HolderArray::swap(self, i, j);
+    }
+}
+
+static BOXED: BoxedPointer = BoxedPointer::new();
+
+/// An atomic pointer that only ever holds null or a pointer obtained from a Box, and that is
+/// bound to the domain every pointer swapped out of it is retired into. Upholding that invariant
+/// in the constructor and in every store is what lets loads, swaps and retirements through it be
+/// safe.
+pub struct HzAtomic<'d, T> {
+    inner: AtomicPtr<T>,
+    domain: &'d Domain,
+    marker: PhantomData<Box<T>>,
+}
+
+unsafe impl<T> Send for HzAtomic<'_, T> where T: Send {}
+unsafe impl<T> Sync for HzAtomic<'_, T> where T: Send + Sync {}
+
+impl<T> Drop for HzAtomic<'_, T> {
+    fn drop(&mut self) {
+        let current = self.inner.load(Ordering::Acquire);
+        if !current.is_null() {
+            let owned = unsafe { Box::from_raw(current) };
+            std::mem::drop(owned);
+        }
+    }
+}
+
+impl<T> HzAtomic<'static, T> {
+    /// Creates an atomic bound to the process wide domain.
+    pub fn new(value: Option<Box<T>>) -> Self {
+        Self::new_in(value, Domain::global())
+    }
+}
+
+impl<'d, T> HzAtomic<'d, T> {
+    pub fn new_in(value: Option<Box<T>>, domain: &'d Domain) -> Self {
+        Self {
+            inner: AtomicPtr::new(Self::into_raw(value)),
+            domain,
+            marker: PhantomData,
+        }
+    }
+
+    pub fn domain(&self) -> &'d Domain {
+        self.domain
+    }
+
+    fn into_raw(value: Option<Box<T>>) -> *mut T {
+        value.map_or(std::ptr::null_mut(), Box::into_raw)
+    }
+
+    /// Loads and protects the current pointer, returning None if it is null.
+    ///
+    /// The guard borrows the atomic as well as the holder, since dropping the atomic frees its
+    /// pointer without going through the domain:
+    ///
+    /// ```compile_fail
+    /// use electron::{Holder, HzAtomic};
+    ///
+    /// let mut holder = Holder::default();
+    /// let guard = {
+    ///     let atomic = HzAtomic::new(Some(Box::new(vec![1u8])));
+    ///     atomic.load(&mut holder).unwrap()
+    /// };
+    /// println!("{:?}", *guard);
+    /// ```
+    ///
+    /// # Panics
+    ///   If the holder is bound to a different domain than this atomic, as its hazard would not be
+    ///   seen by the scans that reclaim the pointers swapped out of here.
+    pub fn load<'a>(&'a self, holder: &'a mut Holder<'_>) -> Option<Guard<'a, T>> {
+        assert!(
+            std::ptr::eq(holder.domain(), self.domain),
+            "The holder is bound to a different domain"
+        );
+        unsafe { holder.load_pointer(&self.inner) }
+    }
+}
+
+impl<'d, T: Send + 'static> HzAtomic<'d, T> {
+    /// Replaces the current pointer and hands back the old one, ready to be retired.
+    pub fn swap(&self, value: Option<Box<T>>) -> Option<DoerWrapper<'d, T>> {
+        let mut holder = Holder::new_in(self.domain);
+        unsafe { holder.swap(&self.inner, Self::into_raw(value), &BOXED) }
+    }
+
+    /// Replaces the current pointer and retires the old one.
+    pub fn store(&self, value: Option<Box<T>>) {
+        if let Some(mut wrapper) = self.swap(value) {
+            wrapper.retire();
+        }
+    }
+
+    /// Stores `new` if the atomic still holds `current`. On success the old pointer is handed back
+    /// ready to be retired, on failure `new` is given back to the caller.
+    #[allow(clippy::type_complexity)]
+    pub fn compare_exchange(
+        &self,
+        current: *mut T,
+        new: Option<Box<T>>,
+    ) -> Result<Option<DoerWrapper<'d, T>>, Option<Box<T>>> {
+        let new = Self::into_raw(new);
+        match self
+            .inner
+            .compare_exchange(current, new, Ordering::AcqRel, Ordering::Acquire)
+        {
+            Ok(old) if old.is_null() => Ok(None),
+            Ok(old) => Ok(Some(DoerWrapper {
+                inner: old,
+                domain: self.domain,
+                cohort: None,
+                deleter: Some(&BOXED),
+            })),
+            Err(_) => Err(unsafe { new.as_mut() }.map(|new| unsafe { Box::from_raw(new) })),
         }
     }
 }
@@ -190,84 +465,411 @@ impl Hazard {
     pub fn protect(&self, ptr: *mut ()) {
         self.ptr.store(ptr, Ordering::Release);
     }
+
+    // Keeps publishing whatever the atomic holds until the hazard is known to have been in place
+    // while the pointer was still stored in it.
+    fn publish<T>(&self, ptr: &AtomicPtr<T>) -> *mut T {
+        self.publish_with(ptr, |ptr| ptr)
+    }
+
+    // Same as publish, but protects the pointer that strip extracts from the stored value, which
+    // lets the value carry tag bits.
+    fn publish_with<T>(&self, ptr: &AtomicPtr<T>, strip: impl Fn(*mut T) -> *mut T) -> *mut T {
+        let mut ptr1 = ptr.load(Ordering::Acquire);
+        loop {
+            self.protect(strip(ptr1) as *mut ());
+            fence::reader();
+            let ptr2 = ptr.load(Ordering::Acquire);
+            if ptr1 == ptr2 {
+                break ptr1;
+            } else {
+                ptr1 = ptr2;
+            }
+        }
+    }
 }
 
 pub trait Doer {
-    fn domain<'a>(&'a self) -> &'a GlobalDomain;
+    fn domain(&self) -> &Domain;
     fn retire(&mut self);
 }
 
-pub struct DoerWrapper<'a, T> {
+/// A pointer swapped out of an atomic, waiting to be retired together with its deleter.
+///
+/// The pointer may be to any type, so unlike Domain::retire this can not use a RetireLink embedded
+/// in the object: every retirement through a wrapper still allocates a link next to the deleter.
+/// Objects that embed a RetireLink should be retired with Domain::retire instead.
+pub struct DoerWrapper<'a, T, D = &'static BoxedPointer> {
     pub(crate) inner: *mut T,
-    domain: &'a GlobalDomain,
-    deleter: &'static dyn Deleter,
+    domain: &'a Domain,
+    cohort: Option<&'a Cohort>,
+    // Taken by the first retirement, deleters may only run once.
+    deleter: Option<D>,
 }
 
-impl<T> Deref for DoerWrapper<'_, T> {
-    type Target = T;
-    fn deref(&self) -> &Self::Target {
-        unsafe { &(*self.inner) }
+impl<'a, T, D: Deleter<T>> DoerWrapper<'a, T, D> {
+    /// Wraps a pointer that has already been unlinked by other means than swapping it out, such as
+    /// a compare and swap on a list, so that retiring it counts against the cohort.
+    ///
+    /// # Safety
+    ///   No reader may be able to reach the pointer anymore except through hazards of the domain.
+    pub(crate) unsafe fn unlinked(
+        inner: *mut T,
+        domain: &'a Domain,
+        cohort: &'a Cohort,
+        deleter: D,
+    ) -> Self {
+        Self {
+            inner,
+            domain,
+            cohort: Some(cohort),
+            deleter: Some(deleter),
+        }
     }
 }
 
-impl<T> DerefMut for DoerWrapper<'_, T> {
-    fn deref_mut(&mut self) -> &mut Self::Target {
-        unsafe { &mut (*self.inner) }
+// Readers may still be looking at a swapped out pointer until it is reclaimed, so the wrapper only
+// gives out shared references, and none at all once the pointer has been retired.
+impl<T, D> Deref for DoerWrapper<'_, T, D> {
+    type Target = T;
+    fn deref(&self) -> &Self::Target {
+        unsafe { self.inner.as_ref() }.expect("The pointer has already been retired")
     }
 }
 
-impl<T> Doer for DoerWrapper<'_, T> {
-    fn domain<'a>(&'a self) -> &'a GlobalDomain {
+impl<T, D: Deleter<T>> Doer for DoerWrapper<'_, T, D> {
+    fn domain(&self) -> &Domain {
         self.domain
     }
 
     ///SAFETY:
-    ///  The user must make sure that a retired pointer is not retired again.
+    ///  The user must make sure that a retired pointer is not retired again. Retiring the same
+    ///  wrapper twice is fine, the second call only runs a reclamation pass.
     fn retire(&mut self) {
+        let domain = self.domain;
         if self.inner.is_null() {
-            let domain = self.domain();
-            unsafe {
-                (&domain.ret).reclaim(&domain.list);
-            }
+            domain.try_reclaim();
             return;
         }
-        let domain = self.domain();
-        let mut current = (&domain.ret.head).load(Ordering::Acquire);
+        let inner = std::mem::replace(&mut self.inner, std::ptr::null_mut());
+        let deleter = self.deleter.take().expect("Set for as long as inner is");
+        // The pointer does not embed a link of its own, so one is allocated next to the deleter.
+        let retired = Box::into_raw(Box::new(Retired {
+            link: RetireLink::new(),
+            ptr: inner,
+            deleter,
+        }));
+        unsafe {
+            domain.push(
+                retired as *mut RetireLink,
+                inner as *mut (),
+                Retired::<T, D>::reclaim_erased,
+                self.cohort.map_or(std::ptr::null(), Cohort::enter),
+            );
+        }
+    }
+}
+
+/// A Domain is a set of hazard slots together with the list of pointers retired against them.
+/// Pointers retired into a domain are only ever checked against the hazards of that domain, so
+/// a data structure that owns its domain never pays for the retirements of any other.
+pub struct Domain {
+    list: HazardList,
+    ret: RetiredList,
+    multiplier: usize,
+    // Set while a BackgroundReclaimer is responsible for scanning, in which case retiring only
+    // wakes it up. None of these take part in protecting anything, so loom does not model them.
+    offloaded: std::sync::atomic::AtomicBool,
+    // Set by the first retirement that wakes the reclaimer and cleared by the reclaimer before it
+    // scans, so that only one retirement per scan goes through the lock.
+    wake_pending: std::sync::atomic::AtomicBool,
+    reclaimer: Mutex<Option<Thread>>,
+}
+
+// No Holder can outlive the borrow of the domain it is bound to, so by the time an owned domain is
+// dropped nothing can be protecting the pointers retired into it.
+impl Drop for Domain {
+    fn drop(&mut self) {
+        unsafe {
+            self.ret.reclaim_all();
+            self.list.free();
+        }
+    }
+}
+
+/// Counters describing how much work a domain has done and how much is still waiting on it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DomainStats {
+    /// Hazard slots allocated so far, whether in use or not.
+    pub hazard_slots: usize,
+    /// Hazard slots currently held by a Holder.
+    pub hazards_in_use: usize,
+    /// Objects retired into the domain that have not been reclaimed yet.
+    pub retired: usize,
+    /// Objects reclaimed over the lifetime of the domain.
+    pub reclaimed: usize,
+    /// Scans of the retired list run so far.
+    pub scans: usize,
+    /// How long the most recent scan took.
+    pub last_scan: Duration,
+}
+
+/// The process wide domain is an ordinary Domain that lives in a static.
+pub type GlobalDomain = Domain;
+
+// With R = k * H retired pointers per scan and at most H of them protected, every scan frees at
+// least (k - 1) * H pointers which is what makes the cost of a retirement amortized constant.
+const DEFAULT_MULTIPLIER: usize = 2;
+
+impl Default for Domain {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Domain {
+    #[cfg(not(loom))]
+    pub const fn new() -> Self {
+        Self::with_threshold(DEFAULT_MULTIPLIER)
+    }
+
+    // The loom atomics can not be constructed in a const context.
+    #[cfg(loom)]
+    pub fn new() -> Self {
+        Self::with_threshold(DEFAULT_MULTIPLIER)
+    }
+
+    /// Creates a domain that only scans its retired list once the number of retired pointers
+    /// reaches `multiplier` times the number of hazard slots. A multiplier of zero scans on every
+    /// retirement.
+    #[cfg(not(loom))]
+    pub const fn with_threshold(multiplier: usize) -> Self {
+        Self {
+            list: HazardList {
+                head: AtomicPtr::new(std::ptr::null_mut()),
+                count: AtomicUsize::new(0),
+                in_use: std::sync::atomic::AtomicUsize::new(0),
+            },
+            ret: RetiredList {
+                head: AtomicPtr::new(std::ptr::null_mut()),
+                count: AtomicUsize::new(0),
+                reclaimed: std::sync::atomic::AtomicUsize::new(0),
+                scans: std::sync::atomic::AtomicUsize::new(0),
+                last_scan: std::sync::atomic::AtomicUsize::new(0),
+            },
+            multiplier,
+            offloaded: std::sync::atomic::AtomicBool::new(false),
+            wake_pending: std::sync::atomic::AtomicBool::new(false),
+            reclaimer: Mutex::new(None),
+        }
+    }
+
+    #[cfg(loom)]
+    pub fn with_threshold(multiplier: usize) -> Self {
+        Self {
+            list: HazardList {
+                head: AtomicPtr::new(std::ptr::null_mut()),
+                count: AtomicUsize::new(0),
+                in_use: std::sync::atomic::AtomicUsize::new(0),
+            },
+            ret: RetiredList {
+                head: AtomicPtr::new(std::ptr::null_mut()),
+                count: AtomicUsize::new(0),
+                reclaimed: std::sync::atomic::AtomicUsize::new(0),
+                scans: std::sync::atomic::AtomicUsize::new(0),
+                last_scan: std::sync::atomic::AtomicUsize::new(0),
+            },
+            multiplier,
+            offloaded: std::sync::atomic::AtomicBool::new(false),
+            wake_pending: std::sync::atomic::AtomicBool::new(false),
+            reclaimer: Mutex::new(None),
+        }
+    }
+
+    /// The domain used by every Holder that was not explicitly bound to another one.
+    pub fn global() -> &'static Domain {
+        &SHARED_DOMAIN
+    }
+
+    /// The number of hazard slots that have ever been allocated in this domain.
+    pub fn hazard_slots(&self) -> usize {
+        self.list.count.load(Ordering::Relaxed)
+    }
+
+    /// A snapshot of the counters of this domain. The counters are read one at a time, so under
+    /// concurrent use they need not be consistent with each other.
+    pub fn stats(&self) -> DomainStats {
+        DomainStats {
+            hazard_slots: self.list.count.load(Ordering::Relaxed),
+            hazards_in_use: self.list.in_use.load(Ordering::Relaxed),
+            retired: self.ret.count.load(Ordering::Relaxed),
+            reclaimed: self.ret.reclaimed.load(Ordering::Relaxed),
+            scans: self.ret.scans.load(Ordering::Relaxed),
+            last_scan: Duration::from_nanos(self.ret.last_scan.load(Ordering::Relaxed) as u64),
+        }
+    }
+
+    /// Reclaims every pointer retired into this domain that is not currently protected.
+    pub fn try_reclaim(&self) {
+        unsafe {
+            self.ret.reclaim(&self.list);
+        }
+    }
+
+    /// Runs the deleter of every pointer retired into this domain whether it is protected or not.
+    ///
+    /// # Safety
+    ///   No thread may be holding, or be about to load, any of the pointers retired into this
+    ///   domain.
+    pub unsafe fn reclaim_all(&self) {
+        unsafe { self.ret.reclaim_all() };
+    }
+
+    /// Force reclaims everything retired into this domain and frees all of its hazard records.
+    /// The domain can be used again afterwards, it simply starts out empty.
+    ///
+    /// # Safety
+    ///   The caller must guarantee quiescence: no Holder of this domain may be alive on any
+    ///   thread, and none of the pointers retired into it may be reachable any more.
+    pub unsafe fn shutdown(&self) {
+        // Only the process wide domain hands its slots to the thread local caches.
+        #[cfg(not(loom))]
+        if self.is_global() {
+            HazardCache::invalidate();
+        }
+        unsafe {
+            self.ret.reclaim_all();
+            self.list.free();
+        }
+    }
+
+    /// Retires an object that embeds its own RetireLink, so no allocation is needed for it. The
+    /// object is reclaimed through Retirable::reclaim once no hazard of this domain protects it.
+    ///
+    /// # Safety
+    ///   The object must be unreachable for any thread that has not already protected it, must not
+    ///   have been retired before and must be valid for Retirable::reclaim.
+    pub unsafe fn retire<T: Retirable>(&self, ptr: *mut T) {
+        unsafe { self.push_link(RetireLink::attach(ptr, None)) };
+    }
+
+    /// Same as retire, but tags the object with the given cohort so that Domain::drain can later
+    /// wait for it to be reclaimed.
+    ///
+    /// # Safety
+    ///   Same as Domain::retire.
+    pub unsafe fn retire_in<T: Retirable>(&self, cohort: &Cohort, ptr: *mut T) {
+        unsafe { self.push_link(RetireLink::attach(ptr, Some(cohort))) };
+    }
+
+    /// Reclaims every object retired into this domain under the given cohort before returning.
+    /// Objects that are still protected are waited on, so this is meant to be called once the
+    /// owner of the cohort is no longer reachable by any other thread, typically in its Drop.
+    pub fn drain(&self, cohort: &Cohort) {
+        while cohort.pending.load(Ordering::Acquire) != 0 {
+            self.try_reclaim();
+            if cohort.pending.load(Ordering::Acquire) != 0 {
+                // Either a hazard still covers one of the objects or another thread took them off
+                // the list and is in the middle of reclaiming them.
+                thread::yield_now();
+            }
+        }
+    }
+
+    unsafe fn push(
+        &self,
+        link: *mut RetireLink,
+        obj: *mut (),
+        reclaim: unsafe fn(*mut RetireLink),
+        cohort: *const AtomicUsize,
+    ) {
+        unsafe {
+            *(*link).obj.get() = obj;
+            *(*link).reclaim.get() = reclaim;
+            *(*link).cohort.get() = cohort;
+            self.push_link(link);
+        }
+    }
+
+    unsafe fn push_link(&self, link: *mut RetireLink) {
+        // Counting before the push keeps a concurrent scan from ever seeing more retired pointers
+        // than the count accounts for.
+        let retired = self.ret.count.fetch_add(1, Ordering::AcqRel) + 1;
+        let mut current = self.ret.head.load(Ordering::Acquire);
         loop {
-            let ret = Retired {
-                ptr: self.inner as *mut dyn Uniform,
-                next: AtomicPtr::new(std::ptr::null_mut()),
-                deleter: self.deleter,
-            };
-            ret.next.store(current, Ordering::Release);
-            let boxed = Box::into_raw(Box::new(ret));
-            if domain
+            unsafe { (*link).next.store(current, Ordering::Relaxed) };
+            match self
                 .ret
                 .head
-                .compare_exchange(current, boxed, Ordering::AcqRel, Ordering::Relaxed)
-                .is_err()
+                .compare_exchange(current, link, Ordering::AcqRel, Ordering::Acquire)
             {
-                let drop = unsafe { Box::from_raw(boxed) };
-                current = (&domain.ret.head).load(Ordering::Acquire);
-                std::mem::drop(drop);
-            } else {
-                unsafe {
-                    (&domain.ret).reclaim(&domain.list);
+                Ok(_) => break,
+                Err(now) => current = now,
+            }
+        }
+        self.reclaim_if_needed(retired);
+    }
+
+    /// Scans the retired list only if enough pointers have piled up since the last scan.
+    fn reclaim_if_needed(&self, retired: usize) {
+        let threshold = self.multiplier * self.list.count.load(Ordering::Relaxed);
+        if retired >= threshold.max(1) {
+            if self.offloaded.load(Ordering::Relaxed) {
+                if !self.wake_pending.load(Ordering::Relaxed)
+                    && !self.wake_pending.swap(true, Ordering::AcqRel)
+                {
+                    self.wake_reclaimer();
                 }
-                break;
+            } else {
+                self.try_reclaim();
             }
         }
     }
-}
 
-pub struct GlobalDomain {
-    list: HazardList,
-    ret: RetiredList,
-}
+    /// Stops retiring threads from scanning themselves, they leave that to a background reclaimer.
+    pub(crate) fn offload(&self, offloaded: bool) {
+        self.offloaded.store(offloaded, Ordering::Relaxed);
+    }
+
+    /// Registers the thread a background reclaimer of this domain is running on.
+    pub(crate) fn set_reclaimer(&self, thread: Option<Thread>) {
+        *self
+            .reclaimer
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner) = thread;
+    }
+
+    /// Runs a scan on behalf of a background reclaimer. Retirements from here on wake it again.
+    pub(crate) fn background_reclaim(&self) {
+        // Reading the flag of the retirement that set it makes its push visible to the scan.
+        self.wake_pending.swap(false, Ordering::AcqRel);
+        self.try_reclaim();
+    }
+
+    pub(crate) fn wake_reclaimer(&self) {
+        if let Some(thread) = &*self
+            .reclaimer
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+        {
+            thread.unpark();
+        }
+    }
+
+    #[cfg(not(loom))]
+    fn is_global(&self) -> bool {
+        std::ptr::eq(self, Domain::global())
+    }
 
-impl GlobalDomain {
-    fn acquire(&self) -> &'static Hazard {
-        let mut current = (&self.list.head).load(Ordering::Acquire);
+    fn acquire(&self) -> &Hazard {
+        self.list.in_use.fetch_add(1, Ordering::Relaxed);
+        #[cfg(not(loom))]
+        if self.is_global()
+            && let Some(hazard) = HazardCache::pop()
+        {
+            return unsafe { &*hazard };
+        }
+        let mut current = self.list.head.load(Ordering::Acquire);
         while !current.is_null() {
             if unsafe { &(*current).flag }
                 .compare_exchange(true, false, Ordering::AcqRel, Ordering::Relaxed)
@@ -275,10 +877,46 @@ impl GlobalDomain {
             {
                 return unsafe { &(*current) };
             } else {
-                current = unsafe { (&(*current).next).load(Ordering::Acquire) };
+                current = unsafe { (*current).next.load(Ordering::Acquire) };
             }
         }
+        self.allocate()
+    }
 
+    /// Takes N slots at once: first from the thread local cache, then from a single walk over the
+    /// list, and only allocates whatever is still missing after that.
+    fn acquire_many<const N: usize>(&self) -> [&Hazard; N] {
+        self.list.in_use.fetch_add(N, Ordering::Relaxed);
+        let mut slots: [*const Hazard; N] = [std::ptr::null(); N];
+        let mut taken = 0;
+        #[cfg(not(loom))]
+        if self.is_global() {
+            while taken < N
+                && let Some(hazard) = HazardCache::pop()
+            {
+                slots[taken] = hazard;
+                taken += 1;
+            }
+        }
+        let mut current = self.list.head.load(Ordering::Acquire);
+        while taken < N && !current.is_null() {
+            if unsafe { &(*current).flag }
+                .compare_exchange(true, false, Ordering::AcqRel, Ordering::Relaxed)
+                .is_ok()
+            {
+                slots[taken] = current;
+                taken += 1;
+            }
+            current = unsafe { (*current).next.load(Ordering::Acquire) };
+        }
+        while taken < N {
+            slots[taken] = self.allocate();
+            taken += 1;
+        }
+        slots.map(|hazard| unsafe { &*hazard })
+    }
+
+    fn allocate(&self) -> &Hazard {
         let mut now = self.list.head.load(Ordering::Acquire);
         loop {
             let new = Hazard {
@@ -294,6 +932,7 @@ impl GlobalDomain {
                 .compare_exchange(now, boxed, Ordering::AcqRel, Ordering::Relaxed)
                 .is_ok()
             {
+                self.list.count.fetch_add(1, Ordering::Relaxed);
                 return unsafe { &*boxed };
             } else {
                 let drop = unsafe { Box::from_raw(boxed) };
@@ -302,39 +941,381 @@ impl GlobalDomain {
             }
         }
     }
+
+    /// Hands a slot back. Slots of the process wide domain are parked in the cache of the
+    /// releasing thread before they are given back to the shared list.
+    fn release(&self, hazard: &Hazard) {
+        self.list.in_use.fetch_sub(1, Ordering::Relaxed);
+        #[cfg(not(loom))]
+        if self.is_global() && HazardCache::push(hazard) {
+            return;
+        }
+        hazard.flag.store(true, Ordering::Release);
+    }
+}
+
+// Every thread keeps a handful of the process wide domain's slots to itself so that acquiring
+// one is a thread local operation instead of a walk over the shared list. Loom models recreate
+// the domain for every execution, so the cache is only used outside of it.
+#[cfg(not(loom))]
+const CACHE_SIZE: usize = 8;
+
+#[cfg(not(loom))]
+thread_local! {
+    static HAZARD_CACHE: RefCell<HazardCache> = const {
+        RefCell::new(HazardCache {
+            slots: [std::ptr::null(); CACHE_SIZE],
+            len: 0,
+            generation: 0,
+        })
+    };
+}
+
+#[cfg(not(loom))]
+struct HazardCache {
+    slots: [*const Hazard; CACHE_SIZE],
+    len: usize,
+    generation: usize,
+}
+
+// Bumped by every shutdown of the process wide domain.
+#[cfg(not(loom))]
+static CACHE_GENERATION: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+
+#[cfg(not(loom))]
+impl HazardCache {
+    // Drops the slots of a cache that was filled before the last shutdown without touching them,
+    // their records have been freed.
+    fn refresh(&mut self) {
+        let generation = CACHE_GENERATION.load(Ordering::Relaxed);
+        if self.generation != generation {
+            self.generation = generation;
+            self.len = 0;
+        }
+    }
+
+    fn invalidate() {
+        CACHE_GENERATION.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn pop() -> Option<*const Hazard> {
+        HAZARD_CACHE
+            .try_with(|cache| {
+                let mut cache = cache.try_borrow_mut().ok()?;
+                cache.refresh();
+                if cache.len == 0 {
+                    return None;
+                }
+                cache.len -= 1;
+                Some(cache.slots[cache.len])
+            })
+            .ok()
+            .flatten()
+    }
+
+    fn push(hazard: &Hazard) -> bool {
+        HAZARD_CACHE
+            .try_with(|cache| {
+                let Ok(mut cache) = cache.try_borrow_mut() else {
+                    return false;
+                };
+                cache.refresh();
+                if cache.len == CACHE_SIZE {
+                    return false;
+                }
+                let len = cache.len;
+                cache.slots[len] = hazard;
+                cache.len += 1;
+                true
+            })
+            .unwrap_or(false)
+    }
+}
+
+#[cfg(not(loom))]
+impl Drop for HazardCache {
+    fn drop(&mut self) {
+        self.refresh();
+        for hazard in &self.slots[..self.len] {
+            unsafe { (**hazard).flag.store(true, Ordering::Release) };
+        }
+    }
 }
 
 pub(crate) struct HazardList {
     head: AtomicPtr<Hazard>,
+    count: AtomicUsize,
+    // Slots held by a Holder, as opposed to sitting free in the list or in a thread local cache.
+    // Only ever read for statistics, so loom does not need to model it.
+    in_use: std::sync::atomic::AtomicUsize,
+}
+
+impl HazardList {
+    /// SAFETY:
+    ///    None of the records may be in use or reachable through a thread local cache.
+    unsafe fn free(&self) {
+        let mut current = self.head.swap(std::ptr::null_mut(), Ordering::AcqRel);
+        let mut freed = 0;
+        while !current.is_null() {
+            let next = unsafe { (*current).next.load(Ordering::Acquire) };
+            let drop = unsafe { Box::from_raw(current) };
+            std::mem::drop(drop);
+            freed += 1;
+            current = next;
+        }
+        self.count.fetch_sub(freed, Ordering::AcqRel);
+        // Whatever was still counted as in use belonged to a Holder that was leaked.
+        self.in_use.store(0, Ordering::Relaxed);
+    }
 }
 
 pub struct RetiredList {
-    head: AtomicPtr<Retired>,
+    head: AtomicPtr<RetireLink>,
+    count: AtomicUsize,
+    // The counters below are only ever read for statistics, so loom does not need to model them.
+    reclaimed: std::sync::atomic::AtomicUsize,
+    scans: std::sync::atomic::AtomicUsize,
+    // In nanoseconds.
+    last_scan: std::sync::atomic::AtomicUsize,
+}
+
+/// The intrusive link through which an object sits in the retired list of a domain, in the spirit
+/// of folly's hazptr_obj_base. Embedding one lets an object be retired without allocating.
+pub struct RetireLink {
+    pub(crate) next: AtomicPtr<RetireLink>,
+    obj: UnsafeCell<*mut ()>,
+    reclaim: UnsafeCell<unsafe fn(*mut RetireLink)>,
+    // The pending counter of the cohort the object is counted against, a reference taken with
+    // Arc::into_raw and released once the object is reclaimed. Null without a cohort.
+    cohort: UnsafeCell<*const AtomicUsize>,
+}
+
+// The cells are only written by the single thread retiring the object and only read by the scan
+// that took the object off the retired list.
+unsafe impl Send for RetireLink {}
+unsafe impl Sync for RetireLink {}
+
+impl Default for RetireLink {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RetireLink {
+    pub fn new() -> Self {
+        Self {
+            next: AtomicPtr::new(std::ptr::null_mut()),
+            obj: UnsafeCell::new(std::ptr::null_mut()),
+            reclaim: UnsafeCell::new(reclaim_nothing),
+            cohort: UnsafeCell::new(std::ptr::null()),
+        }
+    }
+
+    /// Prepares the link embedded in ptr for it to be put on a retired list, counting the object
+    /// against the cohort if there is one.
+    pub(crate) unsafe fn attach<T: Retirable>(ptr: *mut T, cohort: Option<&Cohort>) -> *mut Self {
+        let link = unsafe { (*ptr).retire_link() } as *const RetireLink as *mut RetireLink;
+        let cohort = cohort.map_or(std::ptr::null(), Cohort::enter);
+        unsafe {
+            *(*link).obj.get() = ptr as *mut ();
+            *(*link).reclaim.get() = reclaim_object::<T>;
+            *(*link).cohort.get() = cohort;
+        }
+        link
+    }
+
+    pub(crate) unsafe fn reclaim(link: *mut RetireLink) {
+        unsafe {
+            // The link goes away together with the object, so the cohort has to be read first.
+            let cohort = *(*link).cohort.get();
+            let reclaim = *(*link).reclaim.get();
+            reclaim(link);
+            if !cohort.is_null() {
+                let cohort = Arc::from_raw(cohort);
+                cohort.fetch_sub(1, Ordering::AcqRel);
+            }
+        }
+    }
+}
+
+/// A retire tag tied to the data structure that owns it. Objects retired through
+/// Domain::retire_in are counted against their cohort until they have been reclaimed, which lets
+/// the owner synchronously reclaim all of them with Domain::drain when it is dropped.
+pub struct Cohort {
+    // Every retired object holds a reference to the counter until it is reclaimed, so the counter
+    // outlives the cohort if the owner is dropped without draining.
+    pending: Arc<AtomicUsize>,
 }
 
-pub trait Uniform {}
+impl Default for Cohort {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
-impl<T> Uniform for T {}
+impl Cohort {
+    pub fn new() -> Self {
+        Self {
+            pending: Arc::new(AtomicUsize::new(0)),
+        }
+    }
 
-pub(crate) struct Retired {
-    ptr: *mut dyn Uniform,
-    next: AtomicPtr<Retired>,
-    deleter: &'static dyn Deleter,
+    /// The number of objects retired under this cohort that have not been reclaimed yet.
+    pub fn pending(&self) -> usize {
+        self.pending.load(Ordering::Acquire)
+    }
+
+    // Counts one more retired object, which keeps the returned reference until it is reclaimed.
+    fn enter(&self) -> *const AtomicUsize {
+        self.pending.fetch_add(1, Ordering::AcqRel);
+        Arc::into_raw(Arc::clone(&self.pending))
+    }
 }
 
-pub trait Deleter {
-    fn delete(&self, ptr: *mut dyn Uniform);
+/// Lets a data structure retire its nodes through hazard pointers, into either the process wide
+/// domain or a domain of its own, under a cohort so that they can be drained when it is dropped.
+pub struct HazardReclaimer {
+    domain: Option<Arc<Domain>>,
+    cohort: Cohort,
+}
+
+impl Default for HazardReclaimer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HazardReclaimer {
+    pub fn new() -> Self {
+        Self {
+            domain: None,
+            cohort: Cohort::new(),
+        }
+    }
+
+    pub fn with_domain(domain: Domain) -> Self {
+        Self::with_shared_domain(Arc::new(domain))
+    }
+
+    /// Retires into a domain that is shared with others, such as a BackgroundReclaimer.
+    pub fn with_shared_domain(domain: Arc<Domain>) -> Self {
+        Self {
+            domain: Some(domain),
+            cohort: Cohort::new(),
+        }
+    }
+
+    pub fn domain(&self) -> &Domain {
+        self.domain.as_deref().unwrap_or_else(|| Domain::global())
+    }
+
+    pub(crate) fn cohort(&self) -> &Cohort {
+        &self.cohort
+    }
+}
+
+unsafe impl Reclaimer for HazardReclaimer {
+    type Shield<'a> = Holder<'a>;
+    type Shields<'a, const N: usize> = HolderArray<'a, N>;
+
+    fn shield(&self) -> Holder<'_> {
+        Holder::new_in(self.domain())
+    }
+
+    fn shields<const N: usize>(&self) -> HolderArray<'_, N> {
+        HolderArray::new_in(self.domain())
+    }
+
+    unsafe fn retire<T: Retirable>(&self, ptr: *mut T) {
+        unsafe { self.domain().retire_in(&self.cohort, ptr) };
+    }
+
+    fn drain(&self) -> bool {
+        self.domain().drain(&self.cohort);
+        true
+    }
+}
+
+unsafe fn reclaim_nothing(_: *mut RetireLink) {}
+
+/// # Safety
+///   retire_link must always return the same link embedded in self.
+pub unsafe trait Retirable {
+    fn retire_link(&self) -> &RetireLink;
+
+    /// Frees a retired object once it is no longer protected. Objects are expected to come from
+    /// Box::into_raw unless this is overridden.
+    ///
+    /// # Safety
+    ///   Called exactly once, with the pointer that was retired.
+    unsafe fn reclaim(ptr: *mut Self)
+    where
+        Self: Sized,
+    {
+        let owned = unsafe { Box::from_raw(ptr) };
+        std::mem::drop(owned);
+    }
+}
+
+unsafe fn reclaim_object<T: Retirable>(link: *mut RetireLink) {
+    unsafe { T::reclaim(*(*link).obj.get() as *mut T) };
+}
+
+/// Retires an object into the process wide domain, see Domain::retire.
+///
+/// # Safety
+///   Same as Domain::retire.
+pub unsafe fn retire<T: Retirable>(ptr: *mut T) {
+    unsafe { Domain::global().retire(ptr) };
+}
+
+// The link has to come first so that a pointer to it is a pointer to the whole allocation.
+#[repr(C)]
+struct Retired<T, D> {
+    link: RetireLink,
+    ptr: *mut T,
+    deleter: D,
+}
+
+impl<T, D: Deleter<T>> Retired<T, D> {
+    unsafe fn reclaim_erased(link: *mut RetireLink) {
+        let retired = unsafe { Box::from_raw(link as *mut Retired<T, D>) };
+        let Retired { ptr, deleter, .. } = *retired;
+        unsafe { deleter.delete(ptr) };
+    }
+}
+
+/// Decides what happens to a retired pointer once nothing protects it any more. A deleter may end
+/// up running on any thread, at any later point, hence the Send and 'static bounds.
+pub trait Deleter<T>: Send + 'static {
+    /// # Safety
+    ///   The pointer must have been created in the way the deleter expects and must not be used
+    ///   afterwards.
+    unsafe fn delete(self, ptr: *mut T);
+}
+
+/// Any closure can act as a deleter, which allows retired pointers to be handed back to an object
+/// pool, a slab or a foreign free function instead of the global allocator.
+impl<T, F> Deleter<T> for F
+where
+    F: FnOnce(*mut T) + Send + 'static,
+{
+    unsafe fn delete(self, ptr: *mut T) {
+        self(ptr)
+    }
 }
 
 /// SAFETY:
 ///   1. The user would have to pass an instance of one of the two zero sized types defined below:
-///     DropBox and DropPointer on the basis of how the actual raw pointer to the underlying type
-///     was created. This is necessary because using the drop_in_place() method on every pointer will
-///     not dealloate the instance of the box for all those pointers created using Box::into_raw().
-///   2. The user must create the instance using static as the trait object must have a static
+///      DropBox and DropPointer on the basis of how the actual raw pointer to the underlying type
+///      was created. This is necessary because using the drop_in_place() method on every pointer
+///      will not dealloate the instance of the box for all those pointers created using
+///      Box::into_raw().
+///   2. The user must create the instance using static as the deleter must have a static
 ///      lifetime because we never know when the delete method on that deleter will be called.
 ///      Using static does not come with any memory overhead as the underlying type would be a zero
 ///      sized type.
+#[derive(Default)]
 pub struct BoxedPointer;
 
 impl BoxedPointer {
@@ -343,15 +1324,16 @@ impl BoxedPointer {
     }
 }
 
-impl Deleter for BoxedPointer {
-    fn delete(&self, ptr: *mut dyn Uniform) {
-        if let Some(_) = NonNull::new(ptr) {
+impl<T> Deleter<T> for &'static BoxedPointer {
+    unsafe fn delete(self, ptr: *mut T) {
+        if !ptr.is_null() {
             let drop = unsafe { Box::from_raw(ptr) };
             std::mem::drop(drop);
         }
     }
 }
 
+#[derive(Default)]
 pub struct DropPointer;
 
 impl DropPointer {
@@ -360,9 +1342,9 @@ impl DropPointer {
     }
 }
 
-impl Deleter for DropPointer {
-    fn delete(&self, ptr: *mut dyn Uniform) {
-        if let Some(_) = NonNull::new(ptr) {
+impl<T> Deleter<T> for &'static DropPointer {
+    unsafe fn delete(self, ptr: *mut T) {
+        if !ptr.is_null() {
             unsafe {
                 std::ptr::drop_in_place(ptr);
             }
@@ -371,31 +1353,72 @@ impl Deleter for DropPointer {
 }
 
 impl RetiredList {
+    /// SAFETY:
+    ///    None of the retired pointers may be protected or reachable any more.
+    unsafe fn reclaim_all(&self) {
+        let mut current = self.head.swap(std::ptr::null_mut(), Ordering::AcqRel);
+        let mut reclaimed = 0;
+        while !current.is_null() {
+            let next = unsafe { (*current).next.load(Ordering::Acquire) };
+            unsafe { RetireLink::reclaim(current) };
+            reclaimed += 1;
+            current = next;
+        }
+        self.count.fetch_sub(reclaimed, Ordering::AcqRel);
+        self.reclaimed.fetch_add(reclaimed, Ordering::Relaxed);
+    }
+
     /// SAFETY:
     ///    The user must make sure that the reclaim method is not called on the list of retired
     ///    pointers contaning two similar pointers as this will lead to the same pointers being
     ///    dereferenced leading to undefined behaviour.
-    unsafe fn reclaim<'a>(&self, domain: &'a HazardList) {
-        let mut set = HashSet::new();
-        let mut swapped = (self.head).swap(std::ptr::null_mut(), Ordering::AcqRel);
-        let mut current = (&(domain.head)).load(Ordering::Acquire);
+    unsafe fn reclaim(&self, domain: &HazardList) {
+        // The snapshot buffer is reused across scans so that the steady state does not touch the
+        // allocator. A deleter that retires (and thus scans) again finds the buffer borrowed and
+        // falls back to a fresh one, as does a scan running while the thread is being torn down.
+        let start = Instant::now();
+        let reused = SCAN_BUFFER.try_with(|buffer| {
+            if let Ok(mut buffer) = buffer.try_borrow_mut() {
+                unsafe { self.reclaim_with(domain, &mut buffer) };
+                true
+            } else {
+                false
+            }
+        });
+        if reused != Ok(true) {
+            unsafe { self.reclaim_with(domain, &mut Vec::new()) };
+        }
+        self.scans.fetch_add(1, Ordering::Relaxed);
+        let elapsed = start.elapsed().as_nanos().min(usize::MAX as u128) as usize;
+        self.last_scan.store(elapsed, Ordering::Relaxed);
+    }
+
+    unsafe fn reclaim_with(&self, domain: &HazardList, protected: &mut Vec<*mut ()>) {
+        let mut swapped = self.head.swap(std::ptr::null_mut(), Ordering::AcqRel);
+        fence::scanner();
+        protected.clear();
+        let mut current = domain.head.load(Ordering::Acquire);
         while !current.is_null() {
             let a = unsafe { (*current).ptr.load(Ordering::Acquire) };
-            set.insert(a);
-            current = unsafe { (&(*current).next).load(Ordering::Acquire) };
+            if !a.is_null() {
+                protected.push(a);
+            }
+            current = unsafe { (*current).next.load(Ordering::Acquire) };
         }
-        let mut remaining: *mut Retired = std::ptr::null_mut();
+        protected.sort_unstable();
+        let mut remaining: *mut RetireLink = std::ptr::null_mut();
+        let mut reclaimed = 0;
         while !swapped.is_null() {
-            let check = unsafe { (*swapped).ptr };
-            if !set.contains(&(check as *mut ())) {
-                let deleter = unsafe { (*swapped).deleter };
-                deleter.delete(check);
-                let to_be_dropped = swapped;
-                swapped = unsafe { ((*swapped).next).load(Ordering::Acquire) };
-                let drop = unsafe { Box::from_raw(to_be_dropped) };
-                std::mem::drop(drop);
+            let check = unsafe { *(*swapped).obj.get() };
+            if protected.binary_search(&check).is_err() {
+                // The link lives inside the object, so it has to be stepped over before the object
+                // is reclaimed.
+                let to_be_reclaimed = swapped;
+                swapped = unsafe { (*swapped).next.load(Ordering::Acquire) };
+                unsafe { RetireLink::reclaim(to_be_reclaimed) };
+                reclaimed += 1;
             } else {
-                let next = unsafe { ((*swapped).next).load(Ordering::Acquire) };
+                let next = unsafe { (*swapped).next.load(Ordering::Acquire) };
                 if remaining.is_null() {
                     remaining = swapped;
                     unsafe {
@@ -412,6 +1435,8 @@ impl RetiredList {
                 swapped = next;
             }
         }
+        self.count.fetch_sub(reclaimed, Ordering::AcqRel);
+        self.reclaimed.fetch_add(reclaimed, Ordering::Relaxed);
         // we also need to make sure that we take care of all the pointers that have been retired
         // in the meantime..therefore I came up with this solution
         loop {
@@ -426,21 +1451,18 @@ impl RetiredList {
                 .is_ok()
             {
                 return;
+            } else if remaining.is_null() {
+                remaining = self.head.swap(std::ptr::null_mut(), Ordering::AcqRel);
             } else {
-                if remaining.is_null() {
-                    remaining = self.head.swap(std::ptr::null_mut(), Ordering::AcqRel);
-                } else {
-                    let mut safety_variable = remaining;
-                    while unsafe { !(*safety_variable).next.load(Ordering::Acquire).is_null() } {
-                        safety_variable =
-                            unsafe { (*safety_variable).next.load(Ordering::Acquire) };
-                    }
-                    let to_be_swapped = self.head.swap(std::ptr::null_mut(), Ordering::AcqRel);
-                    unsafe {
-                        (*safety_variable)
-                            .next
-                            .store(to_be_swapped, Ordering::Release);
-                    }
+                let mut safety_variable = remaining;
+                while unsafe { !(*safety_variable).next.load(Ordering::Acquire).is_null() } {
+                    safety_variable = unsafe { (*safety_variable).next.load(Ordering::Acquire) };
+                }
+                let to_be_swapped = self.head.swap(std::ptr::null_mut(), Ordering::AcqRel);
+                unsafe {
+                    (*safety_variable)
+                        .next
+                        .store(to_be_swapped, Ordering::Release);
                 }
             }
         }
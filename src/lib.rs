@@ -1,10 +1,27 @@
+pub mod array_queue;
+pub mod background;
+pub mod backoff;
+pub mod elimination;
+pub mod epoch;
+pub mod error;
+mod fence;
+pub mod hashmap;
 pub mod hazard;
+pub mod list;
+mod pool;
 pub mod queue;
+mod random;
+pub mod reclaim;
 mod runtime;
+pub mod skipmap;
 pub mod stack;
 pub mod sync;
 pub mod threadpool;
 
-pub use crate::hazard::{BoxedPointer, Doer, Holder};
+pub use crate::array_queue::ArrayQueue;
+pub use crate::background::BackgroundReclaimer;
+pub use crate::elimination::EliminationStack;
+pub use crate::error::{PopError, PushError};
+pub use crate::hazard::{BoxedPointer, Doer, Domain, DomainStats, Holder, HolderArray, HzAtomic};
 pub use crate::queue::Queue;
 pub use crate::stack::Stack;
@@ -0,0 +1,309 @@
+use crate::hazard::{Domain, HazardReclaimer, HolderArray, Retirable, RetireLink};
+use crate::reclaim::Reclaimer;
+use crate::sync::atomic::AtomicPtr;
+use std::marker::PhantomData;
+use std::ops::Deref;
+use std::sync::atomic::Ordering;
+
+// The low bit of a next pointer marks its node as logically deleted. Nodes hold an atomic pointer,
+// so they are always aligned enough for the bit to be free.
+const MARK: usize = 1;
+
+pub(crate) fn marked<T>(ptr: *mut T) -> bool {
+    ptr.addr() & MARK != 0
+}
+
+pub(crate) fn mark<T>(ptr: *mut T) -> *mut T {
+    ptr.map_addr(|addr| addr | MARK)
+}
+
+pub(crate) fn unmarked<T>(ptr: *mut T) -> *mut T {
+    ptr.map_addr(|addr| addr & !MARK)
+}
+
+// Slots of the HolderArray a traversal protects its window with.
+pub(crate) const NEXT: usize = 0;
+pub(crate) const CURRENT: usize = 1;
+pub(crate) const PREVIOUS: usize = 2;
+
+struct Node<K, V> {
+    key: K,
+    value: V,
+    next: AtomicPtr<Node<K, V>>,
+    link: RetireLink,
+}
+
+unsafe impl<K, V> Retirable for Node<K, V> {
+    fn retire_link(&self) -> &RetireLink {
+        &self.link
+    }
+}
+
+// Where a search ended: the link that points at current, and current itself, the first node whose
+// key is not less than the one searched for.
+struct Position<K, V> {
+    found: bool,
+    previous: *const AtomicPtr<Node<K, V>>,
+    current: *mut Node<K, V>,
+}
+
+/// An ordered map kept as a lock-free sorted linked list, after Harris and Michael. Removing a
+/// key first marks the next pointer of its node and only then unlinks it, and every traversal
+/// helps unlinking the marked nodes it comes across. Nodes are protected with hazard pointers.
+pub struct List<K, V> {
+    head: AtomicPtr<Node<K, V>>,
+    reclaimer: HazardReclaimer,
+    marker: PhantomData<Node<K, V>>,
+}
+
+unsafe impl<K, V> Send for List<K, V>
+where
+    K: Send + Sync,
+    V: Send + Sync,
+{
+}
+unsafe impl<K, V> Sync for List<K, V>
+where
+    K: Send + Sync,
+    V: Send + Sync,
+{
+}
+
+impl<K, V> Drop for List<K, V> {
+    fn drop(&mut self) {
+        // Marked nodes that have not been unlinked yet are still owned by the list.
+        let mut current = self.head.load(Ordering::Acquire);
+        while !current.is_null() {
+            let owned = unsafe { Box::from_raw(current) };
+            current = unmarked(owned.next.load(Ordering::Acquire));
+        }
+        self.reclaimer.drain();
+    }
+}
+
+impl<K: Ord, V> Default for List<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K, V> List<K, V> {
+    pub fn domain(&self) -> &Domain {
+        self.reclaimer.domain()
+    }
+}
+
+impl<K: Ord, V> List<K, V> {
+    pub fn new() -> Self {
+        Self::with_reclaimer(HazardReclaimer::new())
+    }
+
+    /// Creates a list that retires its nodes into a domain of its own instead of the process wide
+    /// one.
+    pub fn with_domain(domain: Domain) -> Self {
+        Self::with_reclaimer(HazardReclaimer::with_domain(domain))
+    }
+
+    fn with_reclaimer(reclaimer: HazardReclaimer) -> Self {
+        Self {
+            head: AtomicPtr::new(std::ptr::null_mut()),
+            reclaimer,
+            marker: PhantomData,
+        }
+    }
+
+    /// Inserts the pair unless the key is already present, in which case the pair is dropped and
+    /// false is returned.
+    pub fn insert(&self, key: K, value: V) -> bool {
+        let node = Box::into_raw(Box::new(Node {
+            key,
+            value,
+            next: AtomicPtr::new(std::ptr::null_mut()),
+            link: RetireLink::new(),
+        }));
+        let mut holders = HolderArray::new_in(self.domain());
+        loop {
+            let position = self.find(unsafe { &(*node).key }, &mut holders);
+            if position.found {
+                let owned = unsafe { Box::from_raw(node) };
+                std::mem::drop(owned);
+                return false;
+            }
+            unsafe { (*node).next.store(position.current, Ordering::Relaxed) };
+            if unsafe { &*position.previous }
+                .compare_exchange(position.current, node, Ordering::AcqRel, Ordering::Relaxed)
+                .is_ok()
+            {
+                return true;
+            }
+        }
+    }
+
+    /// Removes the key, returning whether it was present.
+    pub fn remove(&self, key: &K) -> bool {
+        let mut holders = HolderArray::new_in(self.domain());
+        loop {
+            let position = self.find(key, &mut holders);
+            if !position.found {
+                return false;
+            }
+            let current = position.current;
+            let next = unsafe { (*current).next.load(Ordering::Acquire) };
+            if marked(next) {
+                // Somebody else is removing it, the next search will find out who won.
+                continue;
+            }
+            // Marking is what removes the key, unlinking is only cleanup.
+            if unsafe { &(*current).next }
+                .compare_exchange(next, mark(next), Ordering::AcqRel, Ordering::Relaxed)
+                .is_err()
+            {
+                continue;
+            }
+            if unsafe { &*position.previous }
+                .compare_exchange(current, next, Ordering::AcqRel, Ordering::Relaxed)
+                .is_ok()
+            {
+                unsafe { self.reclaimer.retire(current) };
+            } else {
+                // Leave it to a search to unlink the node, and make sure it happens.
+                self.find(key, &mut holders);
+            }
+            return true;
+        }
+    }
+
+    pub fn contains(&self, key: &K) -> bool {
+        let mut holders = HolderArray::new_in(self.domain());
+        self.find(key, &mut holders).found
+    }
+
+    /// Returns the value stored under the key, protected for as long as the guard is alive even
+    /// if the key gets removed in the meantime.
+    pub fn get(&self, key: &K) -> Option<Guard<'_, V>> {
+        let mut holders = HolderArray::new_in(self.domain());
+        let position = self.find(key, &mut holders);
+        if position.found {
+            Some(Guard {
+                value: unsafe { &(*position.current).value },
+                _holders: holders,
+            })
+        } else {
+            None
+        }
+    }
+
+    // On return the current node, if any, is protected by the CURRENT slot and the node owning
+    // the previous link, if any, by the PREVIOUS slot.
+    fn find(&self, key: &K, holders: &mut HolderArray<'_, 3>) -> Position<K, V> {
+        'retry: loop {
+            let mut previous: *const AtomicPtr<Node<K, V>> = &self.head;
+            // The head is never marked.
+            let mut current = holders.protect(CURRENT, &self.head);
+            loop {
+                if current.is_null() {
+                    return Position {
+                        found: false,
+                        previous,
+                        current,
+                    };
+                }
+                let next = holders.protect_with(NEXT, unsafe { &(*current).next }, unmarked);
+                // Only if current is still linked after previous was the protection of next
+                // published while next was reachable.
+                if unsafe { &*previous }.load(Ordering::Acquire) != current {
+                    continue 'retry;
+                }
+                if marked(next) {
+                    let next = unmarked(next);
+                    if unsafe { &*previous }
+                        .compare_exchange(current, next, Ordering::AcqRel, Ordering::Relaxed)
+                        .is_err()
+                    {
+                        continue 'retry;
+                    }
+                    unsafe { self.reclaimer.retire(current) };
+                    holders.swap(NEXT, CURRENT);
+                    current = next;
+                } else {
+                    if unsafe { &(*current).key } >= key {
+                        return Position {
+                            found: unsafe { &(*current).key } == key,
+                            previous,
+                            current,
+                        };
+                    }
+                    previous = unsafe { &(*current).next };
+                    holders.swap(CURRENT, PREVIOUS);
+                    holders.swap(NEXT, CURRENT);
+                    current = next;
+                }
+            }
+        }
+    }
+}
+
+/// A value of a List, kept from being reclaimed by the hazard slots the lookup went through.
+pub struct Guard<'d, V> {
+    value: *const V,
+    _holders: HolderArray<'d, 3>,
+}
+
+impl<'d, V> Guard<'d, V> {
+    /// SAFETY:
+    ///    One of the slots has to protect the node the value lives in.
+    pub(crate) unsafe fn new(value: *const V, holders: HolderArray<'d, 3>) -> Self {
+        Self {
+            value,
+            _holders: holders,
+        }
+    }
+}
+
+impl<V> Deref for Guard<'_, V> {
+    type Target = V;
+    fn deref(&self) -> &Self::Target {
+        unsafe { &*self.value }
+    }
+}
+
+/// An ordered set on top of List.
+pub struct Set<K> {
+    list: List<K, ()>,
+}
+
+impl<K: Ord> Default for Set<K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Ord> Set<K> {
+    pub fn new() -> Self {
+        Self { list: List::new() }
+    }
+
+    pub fn with_domain(domain: Domain) -> Self {
+        Self {
+            list: List::with_domain(domain),
+        }
+    }
+
+    pub fn domain(&self) -> &Domain {
+        self.list.domain()
+    }
+
+    /// Returns false if the key was already present.
+    pub fn insert(&self, key: K) -> bool {
+        self.list.insert(key, ())
+    }
+
+    /// Returns whether the key was present.
+    pub fn remove(&self, key: &K) -> bool {
+        self.list.remove(key)
+    }
+
+    pub fn contains(&self, key: &K) -> bool {
+        self.list.contains(key)
+    }
+}
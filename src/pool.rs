@@ -0,0 +1,56 @@
+use crate::array_queue::ArrayQueue;
+use std::mem::MaybeUninit;
+
+// A node that has been dropped in place but whose allocation is kept around for reuse.
+struct Free<T>(*mut MaybeUninit<T>);
+
+unsafe impl<T> Send for Free<T> {}
+
+/// Keeps the allocations of reclaimed nodes on a lock-free free list for the next insertion to
+/// reuse, instead of handing them back to the global allocator. Allocations come from Box either
+/// way, so a node may always be freed with Box::from_raw instead of being recycled.
+pub(crate) struct Pool<T> {
+    free: ArrayQueue<Free<T>>,
+}
+
+impl<T> Drop for Pool<T> {
+    fn drop(&mut self) {
+        while let Some(Free(ptr)) = self.free.pop() {
+            let owned = unsafe { Box::from_raw(ptr) };
+            std::mem::drop(owned);
+        }
+    }
+}
+
+impl<T> Pool<T> {
+    /// Keeps at most capacity free allocations, any further ones are freed. Panics if the
+    /// capacity is zero, containers go without a pool instead.
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            free: ArrayQueue::new(capacity),
+        }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.free.len()
+    }
+
+    pub(crate) fn allocate(&self, value: T) -> *mut T {
+        match self.free.pop() {
+            Some(Free(ptr)) => unsafe { (*ptr).write(value) },
+            None => Box::into_raw(Box::new(value)),
+        }
+    }
+
+    /// SAFETY:
+    ///    The pointer has to come from allocate on any pool of the same type, or from Box, and
+    ///    must not be used afterwards.
+    pub(crate) unsafe fn recycle(&self, ptr: *mut T) {
+        unsafe { std::ptr::drop_in_place(ptr) };
+        if let Err(full) = self.free.push(Free(ptr.cast())) {
+            let Free(ptr) = full.into_inner();
+            let owned = unsafe { Box::from_raw(ptr) };
+            std::mem::drop(owned);
+        }
+    }
+}
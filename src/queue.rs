@@ -4,20 +4,41 @@ use std::mem::MaybeUninit;
 use std::ptr;
 use std::sync::atomic::Ordering;
 
-use crate::{BoxedPointer, Doer, Holder};
-
-static DROPBOX: BoxedPointer = BoxedPointer::new();
+use crate::hazard::{Domain, HazardReclaimer, Retirable, RetireLink};
+use crate::pool::Pool;
+use crate::reclaim::{Reclaimer, Shield, Shields};
 
 struct Node<T> {
     value: MaybeUninit<T>,
     next: AtomicPtr<Node<T>>,
+    link: RetireLink,
+    // The pool of the queue the node goes back to once reclaimed, if the queue has one.
+    pool: *const Pool<Node<T>>,
+}
+
+unsafe impl<T> Retirable for Node<T> {
+    fn retire_link(&self) -> &RetireLink {
+        &self.link
+    }
+
+    unsafe fn reclaim(ptr: *mut Self) {
+        match unsafe { (*ptr).pool.as_ref() } {
+            Some(pool) => unsafe { pool.recycle(ptr) },
+            None => {
+                let owned = unsafe { Box::from_raw(ptr) };
+                std::mem::drop(owned);
+            }
+        }
+    }
 }
 
 impl<T> Node<T> {
-    fn new() -> Self {
+    fn new(pool: *const Pool<Node<T>>) -> Self {
         Self {
             value: MaybeUninit::uninit(),
             next: AtomicPtr::new(ptr::null_mut()),
+            link: RetireLink::new(),
+            pool,
         }
     }
 
@@ -26,56 +47,137 @@ impl<T> Node<T> {
     }
 }
 
-pub struct Queue<T> {
+/// A Michael-Scott queue whose nodes are reclaimed through R, hazard pointers unless chosen
+/// otherwise.
+pub struct Queue<T, R: Reclaimer = HazardReclaimer> {
     head: AtomicPtr<Node<T>>,
     tail: AtomicPtr<Node<T>>,
+    reclaimer: R,
+    // Boxed so that nodes can point at it while the queue moves.
+    pool: Option<Box<Pool<Node<T>>>>,
     marker: PhantomData<Node<T>>,
 }
 
-unsafe impl<T> Send for Queue<T> where T: Send {}
-unsafe impl<T> Sync for Queue<T> where T: Send {}
+unsafe impl<T, R: Reclaimer> Send for Queue<T, R> where T: Send {}
+unsafe impl<T, R: Reclaimer> Sync for Queue<T, R> where T: Send {}
 
-impl<T> Drop for Queue<T> {
+impl<T, R: Reclaimer> Drop for Queue<T, R> {
     fn drop(&mut self) {
+        // The head is the sentinel whose value has either never been written or already been read,
+        // every node after it still holds an element.
         let mut current = self.head.load(Ordering::Acquire);
+        let mut sentinel = true;
         while !current.is_null() {
             let new = unsafe { (*current).next.load(Ordering::Acquire) };
-            let owned = unsafe { Box::from_raw(current) };
+            // Pooled nodes are boxed all the same.
+            let mut owned = unsafe { Box::from_raw(current) };
+            if !sentinel {
+                unsafe { owned.value.assume_init_drop() };
+            }
             std::mem::drop(owned);
+            sentinel = false;
             current = new;
         }
+        // Nodes dequeued earlier are reclaimed before the queue is gone rather than whenever some
+        // other thread happens to scan for them, and before the pool they return to is dropped.
+        if !self.reclaimer.drain() {
+            // The nodes left over still go back to the pool whenever they do get reclaimed.
+            if let Some(pool) = self.pool.take() {
+                Box::leak(pool);
+            }
+        }
     }
 }
 
-impl<T> Default for Queue<T> {
+impl<T, R: Reclaimer + Default> Default for Queue<T, R> {
     fn default() -> Self {
-        Self::new()
+        Self::with_reclaimer(R::default())
     }
 }
 
 impl<T> Queue<T> {
     pub fn new() -> Self {
-        let sentinel_node = Box::into_raw(Box::new(Node::new()));
+        Self::with_reclaimer(HazardReclaimer::new())
+    }
+
+    /// Creates a queue that retires its nodes into a domain of its own instead of the process
+    /// wide one.
+    pub fn with_domain(domain: Domain) -> Self {
+        Self::with_reclaimer(HazardReclaimer::with_domain(domain))
+    }
+
+    /// Creates a queue that recycles up to capacity reclaimed nodes for later enqueues instead of
+    /// freeing them. A capacity of zero creates a queue without a pool, same as Queue::new.
+    pub fn with_pool(capacity: usize) -> Self {
+        Self::with_reclaimer_and_pool(HazardReclaimer::new(), capacity)
+    }
+
+    pub fn domain(&self) -> &Domain {
+        self.reclaimer.domain()
+    }
+}
+
+impl<T, R: Reclaimer> Queue<T, R> {
+    pub fn with_reclaimer(reclaimer: R) -> Self {
+        Self::with_parts(reclaimer, None)
+    }
+
+    /// See Queue::with_pool.
+    pub fn with_reclaimer_and_pool(reclaimer: R, capacity: usize) -> Self {
+        let pool = (capacity > 0).then(|| Box::new(Pool::new(capacity)));
+        Self::with_parts(reclaimer, pool)
+    }
+
+    fn with_parts(reclaimer: R, pool: Option<Box<Pool<Node<T>>>>) -> Self {
+        // The pool is still empty, so the sentinel is allocated the usual way.
+        let sentinel_node = Box::into_raw(Box::new(Node::new(
+            pool.as_deref()
+                .map_or(ptr::null(), |pool| pool as *const Pool<Node<T>>),
+        )));
         Self {
             head: AtomicPtr::new(sentinel_node),
             tail: AtomicPtr::new(sentinel_node),
+            reclaimer,
+            pool,
             marker: PhantomData,
         }
     }
 
+    fn pool(&self) -> *const Pool<Node<T>> {
+        self.pool
+            .as_deref()
+            .map_or(ptr::null(), |pool| pool as *const Pool<Node<T>>)
+    }
+
+    fn allocate(&self, node: Node<T>) -> *mut Node<T> {
+        match &self.pool {
+            Some(pool) => pool.allocate(node),
+            None => Box::into_raw(Box::new(node)),
+        }
+    }
+
+    pub fn reclaimer(&self) -> &R {
+        &self.reclaimer
+    }
+
+    /// The number of reclaimed nodes currently kept for reuse, always zero without a pool.
+    pub fn pooled(&self) -> usize {
+        self.pool.as_ref().map_or(0, |pool| pool.len())
+    }
+
     pub fn enqueue(&self, value: T) {
-        let mut node = Node::new();
+        let mut node = Node::new(self.pool());
         node.write(value);
-        let allocated = Box::into_raw(Box::new(node));
+        let allocated = self.allocate(node);
         loop {
-            let mut holder = Holder::default();
-            let guard = unsafe {
-                holder
-                    .load_pointer(&self.tail)
-                    .expect("Sentinel node guarantees that the tail pointer is never null")
-            };
+            let mut shield = self.reclaimer.shield();
+            let tail = shield.protect(&self.tail);
+            debug_assert!(
+                !tail.is_null(),
+                "Sentinel node guarantees that the tail pointer is never null"
+            );
             let cas_result = unsafe {
-                (*guard.data).next.compare_exchange(
+                (*tail).next.compare_exchange(
                     ptr::null_mut(),
                     allocated,
                     Ordering::AcqRel,
@@ -84,7 +186,7 @@ impl<T> Queue<T> {
             };
             if cas_result.is_ok() {
                 let _ = self.tail.compare_exchange(
-                    guard.data,
+                    tail,
                     allocated,
                     Ordering::AcqRel,
                     Ordering::Relaxed,
@@ -94,55 +196,33 @@ impl<T> Queue<T> {
         }
     }
 
-    pub fn dequeue(&self) -> Result<T, &str> {
+    /// Returns None if the queue is empty.
+    pub fn dequeue(&self) -> Option<T> {
         loop {
-            let mut current_head_holder = Holder::default();
-            let mut next_node_holder = Holder::default();
-            let current_head_guard = unsafe {
-                current_head_holder
-                    .load_pointer(&self.head)
-                    .expect("Sentiled node will never allow it to be null")
-            };
-            let next_node_guard = if let Some(guard) =
-                unsafe { next_node_holder.load_pointer(&(*current_head_guard.data).next) }
-            {
-                guard
-            } else {
-                return Err("There are no elements in the queue");
-            };
-            let mut tail_holder = Holder::default();
-            let tail_guard = unsafe {
-                tail_holder
-                    .load_pointer(&self.tail)
-                    .expect("Has to be there")
-            };
-            if tail_guard.data == current_head_guard.data {
-                let _ = self.tail.compare_exchange(
-                    tail_guard.data,
-                    next_node_guard.data,
-                    Ordering::AcqRel,
-                    Ordering::Relaxed,
-                );
+            let mut shields = self.reclaimer.shields::<3>();
+            let head = shields.protect(0, &self.head);
+            debug_assert!(
+                !head.is_null(),
+                "Sentiled node will never allow it to be null"
+            );
+            let next = shields.protect(1, unsafe { &(*head).next });
+            if next.is_null() {
+                return None;
+            }
+            let tail = shields.protect(2, &self.tail);
+            if tail == head {
+                let _ = self
+                    .tail
+                    .compare_exchange(tail, next, Ordering::AcqRel, Ordering::Relaxed);
             }
             if self
                 .head
-                .compare_exchange(
-                    current_head_guard.data,
-                    next_node_guard.data,
-                    Ordering::AcqRel,
-                    Ordering::Relaxed,
-                )
+                .compare_exchange(head, next, Ordering::AcqRel, Ordering::Relaxed)
                 .is_ok()
             {
-                let read_value = unsafe { (*next_node_guard.data).value.assume_init_read() };
-                let mut swap_holder = Holder::default();
-                let wrapper = unsafe {
-                    swap_holder.get_wrapper(&AtomicPtr::new(current_head_guard.data), &DROPBOX)
-                };
-                if let Some(mut wrapper) = wrapper {
-                    wrapper.retire();
-                }
-                return Ok(read_value);
+                let read_value = unsafe { (*next).value.assume_init_read() };
+                unsafe { self.reclaimer.retire(head) };
+                return Some(read_value);
             }
         }
     }
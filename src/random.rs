@@ -0,0 +1,18 @@
+use std::cell::Cell;
+use std::hash::{BuildHasher, RandomState};
+
+/// A cheap per-thread xorshift generator, for decisions that only need to be spread out rather
+/// than unpredictable.
+pub(crate) fn next() -> u64 {
+    thread_local! {
+        static SEED: Cell<u64> = Cell::new(RandomState::new().hash_one(0u8) | 1);
+    }
+    SEED.with(|seed| {
+        let mut x = seed.get();
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        seed.set(x);
+        x
+    })
+}
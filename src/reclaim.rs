@@ -0,0 +1,50 @@
+use crate::hazard::Retirable;
+use crate::sync::atomic::AtomicPtr;
+
+/// A memory reclamation scheme the data structures of this crate can retire their nodes through.
+/// It is implemented by hazard::HazardReclaimer and epoch::EpochReclaimer, which trade a hazard
+/// publication per protected load against objects possibly staying around for longer.
+///
+/// # Safety
+///   A pointer returned by Shield::protect or Shields::protect must not be reclaimed while the shield is alive, as long
+///   as the object was unlinked before it was passed to retire.
+pub unsafe trait Reclaimer: Send + Sync {
+    type Shield<'a>: Shield
+    where
+        Self: 'a;
+
+    type Shields<'a, const N: usize>: Shields
+    where
+        Self: 'a;
+
+    /// Creates a shield a data structure protects the pointers it is about to dereference with.
+    fn shield(&self) -> Self::Shield<'_>;
+
+    /// Creates N shields at once, for operations that need several pointers protected together.
+    fn shields<const N: usize>(&self) -> Self::Shields<'_, N>;
+
+    /// Hands an unlinked object over to be reclaimed once no shield can be protecting it any more.
+    ///
+    /// # Safety
+    ///   Same as Domain::retire.
+    unsafe fn retire<T: Retirable>(&self, ptr: *mut T);
+
+    /// Reclaims every object retired through this reclaimer before returning. Returns false if
+    /// that could not be done without waiting on the calling thread itself, in which case the
+    /// remaining objects are reclaimed later like any other retired object.
+    fn drain(&self) -> bool;
+}
+
+pub trait Shield {
+    /// Loads the pointer stored in src and keeps it from being reclaimed until the shield is
+    /// dropped or protects another pointer.
+    fn protect<T>(&mut self, src: &AtomicPtr<T>) -> *mut T;
+}
+
+pub trait Shields {
+    /// Same as Shield::protect, through the shield at index.
+    fn protect<T>(&mut self, index: usize, src: &AtomicPtr<T>) -> *mut T;
+
+    /// Exchanges what the shields at i and j protect.
+    fn swap(&mut self, i: usize, j: usize);
+}
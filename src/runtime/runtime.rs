@@ -62,14 +62,14 @@ impl Runtime {
             let handle = std::thread::spawn(move || {
                 loop {
                     if f.load(Ordering::Relaxed) {
-                        if let Ok(carrier) = LOW_QUEUE.dequeue() {
+                        if let Some(carrier) = LOW_QUEUE.dequeue() {
                             let metadata = carrier.data as *const Metadata;
                             unsafe {
                                 ((*metadata).func)(metadata as *const ());
                             }
                         }
                     } else {
-                        while let Ok(carrier) = LOW_QUEUE.dequeue() {
+                        while let Some(carrier) = LOW_QUEUE.dequeue() {
                             let metadata = carrier.data as *const Metadata;
                             unsafe {
                                 ((*metadata).func)(metadata as *const ());
@@ -86,14 +86,14 @@ impl Runtime {
             let handle = std::thread::spawn(move || {
                 loop {
                     if f.load(Ordering::Relaxed) {
-                        if let Ok(carrier) = HIGH_QUEUE.dequeue() {
+                        if let Some(carrier) = HIGH_QUEUE.dequeue() {
                             let metadata = carrier.data as *const Metadata;
                             unsafe {
                                 ((*metadata).func)(metadata as *const ());
                             }
                         }
                     } else {
-                        while let Ok(carrier) = HIGH_QUEUE.dequeue() {
+                        while let Some(carrier) = HIGH_QUEUE.dequeue() {
                             let metadata = carrier.data as *const Metadata;
                             unsafe {
                                 ((*metadata).func)(metadata as *const ());
@@ -0,0 +1,515 @@
+use crate::hazard::{Domain, HazardReclaimer, Holder, HolderArray, Retirable, RetireLink};
+use crate::list::{CURRENT, Guard, NEXT, PREVIOUS, mark, marked, unmarked};
+use crate::random;
+use crate::reclaim::{Reclaimer, Shield};
+use crate::sync::atomic::{AtomicPtr, AtomicUsize};
+use std::marker::PhantomData;
+use std::ops::{Bound, RangeBounds};
+use std::sync::atomic::Ordering;
+
+// With every level half as populated as the one below it, 32 levels are enough for any map that
+// fits into memory.
+const MAX_HEIGHT: usize = 32;
+
+struct Node<K, V> {
+    key: K,
+    value: V,
+    // One reference per level the node is linked at, plus one held by the inserting thread until
+    // it is done linking. The node is retired once the last of them is gone.
+    refs: AtomicUsize,
+    tower: Box<[AtomicPtr<Node<K, V>>]>,
+    link: RetireLink,
+}
+
+unsafe impl<K, V> Retirable for Node<K, V> {
+    fn retire_link(&self) -> &RetireLink {
+        &self.link
+    }
+}
+
+// The link at the given level of a tower, either the head of the map or that of a node.
+fn link<'a, K, V>(tower: *const AtomicPtr<Node<K, V>>, level: usize) -> &'a AtomicPtr<Node<K, V>> {
+    unsafe { &*tower.add(level) }
+}
+
+// Geometrically distributed, so that every level holds about half the nodes of the one below.
+fn random_height() -> usize {
+    (random::next().trailing_ones() as usize + 1).min(MAX_HEIGHT)
+}
+
+// Where a search ended at the level it was asked for: current is the first node that is no longer
+// before the key, and previous the tower linking to it. previous_node is null when that tower is
+// the head of the map.
+struct Position<K, V> {
+    previous: *const AtomicPtr<Node<K, V>>,
+    previous_node: *mut Node<K, V>,
+    current: *mut Node<K, V>,
+}
+
+/// An ordered map kept as a lock-free skiplist. Every level is a Harris-Michael list: removing a
+/// key marks the links of its node from the top down, the mark at the bottom level being the one
+/// that decides who removed it, and searches unlink the marked nodes they pass. Nodes are
+/// protected with hazard pointers, three per search.
+pub struct SkipMap<K, V> {
+    head: [AtomicPtr<Node<K, V>>; MAX_HEIGHT],
+    len: AtomicUsize,
+    reclaimer: HazardReclaimer,
+    marker: PhantomData<Node<K, V>>,
+}
+
+unsafe impl<K, V> Send for SkipMap<K, V>
+where
+    K: Send + Sync,
+    V: Send + Sync,
+{
+}
+unsafe impl<K, V> Sync for SkipMap<K, V>
+where
+    K: Send + Sync,
+    V: Send + Sync,
+{
+}
+
+impl<K, V> Drop for SkipMap<K, V> {
+    fn drop(&mut self) {
+        // Every node that has not been retired is still linked at the bottom level.
+        let mut current = self.head[0].load(Ordering::Acquire);
+        while !current.is_null() {
+            let owned = unsafe { Box::from_raw(current) };
+            current = unmarked(owned.tower[0].load(Ordering::Acquire));
+        }
+        self.reclaimer.drain();
+    }
+}
+
+impl<K: Ord, V> Default for SkipMap<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K, V> SkipMap<K, V> {
+    pub fn domain(&self) -> &Domain {
+        self.reclaimer.domain()
+    }
+
+    pub fn len(&self) -> usize {
+        self.len.load(Ordering::Relaxed)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<K: Ord, V> SkipMap<K, V> {
+    pub fn new() -> Self {
+        Self::with_reclaimer(HazardReclaimer::new())
+    }
+
+    /// Creates a map that retires its nodes into a domain of its own instead of the process wide
+    /// one.
+    pub fn with_domain(domain: Domain) -> Self {
+        Self::with_reclaimer(HazardReclaimer::with_domain(domain))
+    }
+
+    fn with_reclaimer(reclaimer: HazardReclaimer) -> Self {
+        Self {
+            head: std::array::from_fn(|_| AtomicPtr::new(std::ptr::null_mut())),
+            len: AtomicUsize::new(0),
+            reclaimer,
+            marker: PhantomData,
+        }
+    }
+
+    /// Inserts the pair unless the key is already present, in which case the pair is dropped and
+    /// false is returned.
+    pub fn insert(&self, key: K, value: V) -> bool {
+        let height = random_height();
+        let node = Box::into_raw(Box::new(Node {
+            key,
+            value,
+            refs: AtomicUsize::new(1),
+            tower: (0..height)
+                .map(|_| AtomicPtr::new(std::ptr::null_mut()))
+                .collect(),
+            link: RetireLink::new(),
+        }));
+        // The reference of this thread keeps the node alive until it is done with it, even if it
+        // gets removed in the meantime.
+        let key = unsafe { &(*node).key };
+        let mut holders = HolderArray::new_in(self.domain());
+        loop {
+            let position = self.search(0, |current| current < key, &mut holders);
+            if !position.current.is_null() && unsafe { &(*position.current).key } == key {
+                let owned = unsafe { Box::from_raw(node) };
+                std::mem::drop(owned);
+                return false;
+            }
+            unsafe { (*node).tower[0].store(position.current, Ordering::Relaxed) };
+            unsafe { (*node).refs.fetch_add(1, Ordering::Relaxed) };
+            if link(position.previous, 0)
+                .compare_exchange(position.current, node, Ordering::AcqRel, Ordering::Relaxed)
+                .is_ok()
+            {
+                break;
+            }
+            unsafe { (*node).refs.fetch_sub(1, Ordering::Relaxed) };
+        }
+        self.len.fetch_add(1, Ordering::Relaxed);
+        'levels: for level in 1..height {
+            loop {
+                let position = self.search(level, |current| current < key, &mut holders);
+                // Only a removal writes to the tower of a node that is not linked at that level
+                // yet, and it only ever marks it.
+                let next = unsafe { (*node).tower[level].load(Ordering::Acquire) };
+                if marked(next)
+                    || unsafe { &(*node).tower[level] }
+                        .compare_exchange(
+                            next,
+                            position.current,
+                            Ordering::AcqRel,
+                            Ordering::Relaxed,
+                        )
+                        .is_err()
+                {
+                    break 'levels;
+                }
+                unsafe { (*node).refs.fetch_add(1, Ordering::Relaxed) };
+                if link(position.previous, level)
+                    .compare_exchange(position.current, node, Ordering::AcqRel, Ordering::Relaxed)
+                    .is_ok()
+                {
+                    if marked(unsafe { (*node).tower[level].load(Ordering::Acquire) }) {
+                        // Removed while being linked here, after the remover already searched
+                        // past this level, so unlinking it again is up to this thread.
+                        self.search(0, |current| current < key, &mut holders);
+                        break 'levels;
+                    }
+                    break;
+                }
+                unsafe { (*node).refs.fetch_sub(1, Ordering::Relaxed) };
+            }
+        }
+        unsafe { self.release(node) };
+        true
+    }
+
+    /// Removes the key, returning whether it was present.
+    pub fn remove(&self, key: &K) -> bool {
+        let mut holders = HolderArray::new_in(self.domain());
+        let position = self.search(0, |current| current < key, &mut holders);
+        let node = position.current;
+        if node.is_null() || unsafe { &(*node).key } != key || !unsafe { self.mark(node) } {
+            return false;
+        }
+        self.len.fetch_sub(1, Ordering::Relaxed);
+        self.search(0, |current| current < key, &mut holders);
+        true
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        let mut holders = HolderArray::new_in(self.domain());
+        let current = self
+            .search(0, |current| current < key, &mut holders)
+            .current;
+        !current.is_null() && unsafe { &(*current).key } == key
+    }
+
+    /// Returns the value stored under the key, protected for as long as the guard is alive even
+    /// if the key gets removed in the meantime.
+    pub fn get(&self, key: &K) -> Option<Guard<'_, V>> {
+        let mut holders = HolderArray::new_in(self.domain());
+        let current = self
+            .search(0, |current| current < key, &mut holders)
+            .current;
+        if !current.is_null() && unsafe { &(*current).key } == key {
+            Some(unsafe { Guard::new(&(*current).value, holders) })
+        } else {
+            None
+        }
+    }
+
+    /// The entry with the smallest key.
+    pub fn first(&self) -> Option<Entry<'_, K, V>> {
+        let mut holders = HolderArray::new_in(self.domain());
+        let current = self.search(0, |_| false, &mut holders).current;
+        if current.is_null() {
+            None
+        } else {
+            Some(Entry {
+                node: current,
+                _protection: Protection::Search { _holders: holders },
+            })
+        }
+    }
+
+    /// The entry with the largest key.
+    pub fn last(&self) -> Option<Entry<'_, K, V>> {
+        let mut holders = HolderArray::new_in(self.domain());
+        let last = self.search(0, |_| true, &mut holders).previous_node;
+        if last.is_null() {
+            None
+        } else {
+            Some(Entry {
+                node: last,
+                _protection: Protection::Search { _holders: holders },
+            })
+        }
+    }
+
+    /// Removes the entry with the smallest key and returns it. The entry stays readable until it
+    /// is dropped.
+    pub fn pop_first(&self) -> Option<Entry<'_, K, V>> {
+        loop {
+            let entry = self.first()?;
+            if unsafe { self.mark(entry.node) } {
+                self.len.fetch_sub(1, Ordering::Relaxed);
+                let mut holders = HolderArray::new_in(self.domain());
+                self.search(0, |current| current < entry.key(), &mut holders);
+                return Some(entry);
+            }
+        }
+    }
+
+    /// Iterates over the entries whose keys fall into the range, in ascending order. The iterator
+    /// keeps the node it stopped at protected, so it can move on from there even if that node
+    /// gets removed, and every entry it hands out is protected on its own.
+    pub fn range<R: RangeBounds<K>>(&self, range: R) -> Range<'_, K, V, R> {
+        Range {
+            map: self,
+            range,
+            holders: HolderArray::new_in(self.domain()),
+            previous: std::ptr::null(),
+            previous_node: std::ptr::null_mut(),
+            finished: false,
+        }
+    }
+
+    pub fn iter(&self) -> Range<'_, K, V, std::ops::RangeFull> {
+        self.range(..)
+    }
+
+    /// Marks the node as removed from the top level down. Returns whether this thread was the one
+    /// to mark the bottom level, and thus the one that removed the key.
+    ///
+    /// SAFETY:
+    ///    The node has to be protected.
+    unsafe fn mark(&self, node: *mut Node<K, V>) -> bool {
+        let tower = unsafe { &(*node).tower };
+        for level in (0..tower.len()).rev() {
+            let mut next = tower[level].load(Ordering::Acquire);
+            loop {
+                if marked(next) {
+                    if level == 0 {
+                        return false;
+                    }
+                    break;
+                }
+                match tower[level].compare_exchange(
+                    next,
+                    mark(next),
+                    Ordering::AcqRel,
+                    Ordering::Acquire,
+                ) {
+                    Ok(_) => break,
+                    Err(now) => next = now,
+                }
+            }
+        }
+        true
+    }
+
+    /// SAFETY:
+    ///    Called once for every reference the node was given.
+    unsafe fn release(&self, node: *mut Node<K, V>) {
+        if unsafe { (*node).refs.fetch_sub(1, Ordering::AcqRel) } == 1 {
+            unsafe { self.reclaimer.retire(node) };
+        }
+    }
+
+    /// Unlinks current, which is marked at this level, from previous. Returns false if previous
+    /// no longer links to it.
+    fn unlink(
+        &self,
+        previous: &AtomicPtr<Node<K, V>>,
+        current: *mut Node<K, V>,
+        next: *mut Node<K, V>,
+    ) -> bool {
+        if previous
+            .compare_exchange(current, next, Ordering::AcqRel, Ordering::Relaxed)
+            .is_ok()
+        {
+            unsafe { self.release(current) };
+            true
+        } else {
+            false
+        }
+    }
+
+    // Walks down from the top level, moving right at every level for as long as before holds for
+    // the key of the next node, and unlinking the marked nodes on the way. On return current, if
+    // any, is protected by the CURRENT slot and previous_node, if any, by the PREVIOUS slot.
+    fn search(
+        &self,
+        target: usize,
+        before: impl Fn(&K) -> bool,
+        holders: &mut HolderArray<'_, 3>,
+    ) -> Position<K, V> {
+        'retry: loop {
+            let mut previous: *const AtomicPtr<Node<K, V>> = self.head.as_ptr();
+            let mut previous_node: *mut Node<K, V> = std::ptr::null_mut();
+            let mut current = std::ptr::null_mut();
+            for level in (target..MAX_HEIGHT).rev() {
+                current = holders.protect_with(CURRENT, link(previous, level), unmarked);
+                loop {
+                    if marked(current) {
+                        // The node we are moving right from is being removed.
+                        continue 'retry;
+                    }
+                    if current.is_null() {
+                        break;
+                    }
+                    let tower = unsafe { (*current).tower.as_ptr() };
+                    let next = holders.protect_with(NEXT, link(tower, level), unmarked);
+                    if link(previous, level).load(Ordering::Acquire) != current {
+                        continue 'retry;
+                    }
+                    if marked(next) {
+                        if !self.unlink(link(previous, level), current, unmarked(next)) {
+                            continue 'retry;
+                        }
+                        holders.swap(NEXT, CURRENT);
+                        current = unmarked(next);
+                        continue;
+                    }
+                    if !before(unsafe { &(*current).key }) {
+                        break;
+                    }
+                    previous = tower;
+                    previous_node = current;
+                    holders.swap(CURRENT, PREVIOUS);
+                    holders.swap(NEXT, CURRENT);
+                    current = next;
+                }
+            }
+            return Position {
+                previous,
+                previous_node,
+                current,
+            };
+        }
+    }
+}
+
+enum Protection<'d> {
+    Search { _holders: HolderArray<'d, 3> },
+    Single { _holder: Holder<'d> },
+}
+
+/// A key and its value in a SkipMap, kept from being reclaimed for as long as the entry is alive.
+pub struct Entry<'d, K, V> {
+    node: *mut Node<K, V>,
+    _protection: Protection<'d>,
+}
+
+impl<K, V> Entry<'_, K, V> {
+    pub fn key(&self) -> &K {
+        unsafe { &(*self.node).key }
+    }
+
+    pub fn value(&self) -> &V {
+        unsafe { &(*self.node).value }
+    }
+}
+
+fn before_start<K: Ord>(start: Bound<&K>, key: &K) -> bool {
+    match start {
+        Bound::Included(start) => key < start,
+        Bound::Excluded(start) => key <= start,
+        Bound::Unbounded => false,
+    }
+}
+
+fn beyond_end<K: Ord>(end: Bound<&K>, key: &K) -> bool {
+    match end {
+        Bound::Included(end) => key > end,
+        Bound::Excluded(end) => key >= end,
+        Bound::Unbounded => false,
+    }
+}
+
+/// An iterator over a range of a SkipMap, see SkipMap::range.
+pub struct Range<'d, K, V, R> {
+    map: &'d SkipMap<K, V>,
+    range: R,
+    holders: HolderArray<'d, 3>,
+    // The tower the iterator moves on from, protected by the PREVIOUS slot unless it is the head.
+    previous: *const AtomicPtr<Node<K, V>>,
+    previous_node: *mut Node<K, V>,
+    finished: bool,
+}
+
+impl<'d, K: Ord, V, R: RangeBounds<K>> Iterator for Range<'d, K, V, R> {
+    type Item = Entry<'d, K, V>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+        if self.previous.is_null() {
+            let start = self.range.start_bound();
+            let position = self
+                .map
+                .search(0, |key| before_start(start, key), &mut self.holders);
+            self.previous = position.previous;
+            self.previous_node = position.previous_node;
+        }
+        loop {
+            let current = self
+                .holders
+                .protect_with(CURRENT, link(self.previous, 0), unmarked);
+            if marked(current) {
+                // The node the iterator stopped at got removed, so find the first one after its
+                // key all over again. It stays protected by the old slots until that is done.
+                let mut holders = HolderArray::new_in(self.map.domain());
+                let key = unsafe { &(*self.previous_node).key };
+                let position = self.map.search(0, |current| current <= key, &mut holders);
+                self.holders = holders;
+                self.previous = position.previous;
+                self.previous_node = position.previous_node;
+                continue;
+            }
+            if current.is_null() {
+                self.finished = true;
+                return None;
+            }
+            let tower = unsafe { (*current).tower.as_ptr() };
+            let next = self.holders.protect_with(NEXT, link(tower, 0), unmarked);
+            if link(self.previous, 0).load(Ordering::Acquire) != current {
+                continue;
+            }
+            if marked(next) {
+                self.map
+                    .unlink(link(self.previous, 0), current, unmarked(next));
+                continue;
+            }
+            if beyond_end(self.range.end_bound(), unsafe { &(*current).key }) {
+                self.finished = true;
+                return None;
+            }
+            // The entry gets a slot of its own, published and validated like any other load.
+            let mut holder = Holder::new_in(self.map.domain());
+            if Shield::protect(&mut holder, link(self.previous, 0)) != current {
+                continue;
+            }
+            self.holders.swap(CURRENT, PREVIOUS);
+            self.previous = tower;
+            self.previous_node = current;
+            return Some(Entry {
+                node: current,
+                _protection: Protection::Single { _holder: holder },
+            });
+        }
+    }
+}
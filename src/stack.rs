@@ -1,119 +1,264 @@
+use crate::backoff::{Backoff, Exponential};
+use crate::error::{PopError, PushError};
+use crate::hazard::{Domain, HazardReclaimer, Retirable, RetireLink};
+use crate::pool::Pool;
+use crate::reclaim::{Reclaimer, Shield};
 use crate::sync::atomic::AtomicPtr;
-use crate::{BoxedPointer, Doer, Holder};
 use std::marker::PhantomData;
+use std::mem::ManuallyDrop;
 use std::sync::atomic::Ordering;
 
-static DROPBOX: BoxedPointer = BoxedPointer::new();
+// How many compare and swaps try_push and try_pop attempt before giving up.
+const ATTEMPTS: usize = 16;
 
-struct Node<T> {
-    value: T,
+pub(crate) struct Node<T> {
+    // The value is moved out by whoever pops the node, so retiring the node must not drop it again.
+    value: ManuallyDrop<T>,
     next: AtomicPtr<Node<T>>,
+    link: RetireLink,
+    // The pool of the stack the node goes back to once reclaimed, if the stack has one.
+    pool: *const Pool<Node<T>>,
 }
 
-impl<T: Clone> Node<T> {
-    fn new(value: T) -> Self {
+unsafe impl<T> Retirable for Node<T> {
+    fn retire_link(&self) -> &RetireLink {
+        &self.link
+    }
+
+    unsafe fn reclaim(ptr: *mut Self) {
+        match unsafe { (*ptr).pool.as_ref() } {
+            Some(pool) => unsafe { pool.recycle(ptr) },
+            None => {
+                let owned = unsafe { Box::from_raw(ptr) };
+                std::mem::drop(owned);
+            }
+        }
+    }
+}
+
+impl<T> Node<T> {
+    fn new(value: T, pool: *const Pool<Node<T>>) -> Self {
         Self {
-            value,
+            value: ManuallyDrop::new(value),
             next: AtomicPtr::new(std::ptr::null_mut()),
+            link: RetireLink::new(),
+            pool,
         }
     }
+
+    /// Takes the value out of a node and frees the node.
+    ///
+    /// SAFETY:
+    ///    The node must never have been published, or no other thread may be able to reach it.
+    pub(crate) unsafe fn into_value(ptr: *mut Self) -> T {
+        let value = unsafe { ManuallyDrop::take(&mut (*ptr).value) };
+        unsafe { Node::reclaim(ptr) };
+        value
+    }
 }
 
-pub struct Stack<T> {
+/// A Treiber stack whose nodes are reclaimed through R, hazard pointers unless chosen otherwise.
+/// Threads that lose the race for the head wait according to B before retrying.
+pub struct Stack<T, R: Reclaimer = HazardReclaimer, B: Backoff = Exponential> {
     head: AtomicPtr<Node<T>>,
-    marker: PhantomData<Node<T>>,
+    reclaimer: R,
+    // Boxed so that nodes can point at it while the stack moves.
+    pool: Option<Box<Pool<Node<T>>>>,
+    marker: PhantomData<(Node<T>, B)>,
 }
 
-unsafe impl<T> Send for Stack<T> where T: Send {}
-unsafe impl<T> Sync for Stack<T> where T: Send {}
+unsafe impl<T, R: Reclaimer, B: Backoff> Send for Stack<T, R, B> where T: Send {}
+unsafe impl<T, R: Reclaimer, B: Backoff> Sync for Stack<T, R, B> where T: Send {}
 
-impl<T> Drop for Stack<T> {
+impl<T, R: Reclaimer, B: Backoff> Drop for Stack<T, R, B> {
     fn drop(&mut self) {
         let mut current = self.head.load(Ordering::Acquire);
         while !current.is_null() {
             let next = unsafe { (*current).next.load(Ordering::Acquire) };
-            let owned = unsafe { Box::from_raw(current) };
-            std::mem::drop(owned);
+            // Pooled nodes are boxed all the same.
+            let mut owned = unsafe { Box::from_raw(current) };
+            unsafe { ManuallyDrop::drop(&mut owned.value) };
             current = next;
         }
+        // Nodes popped earlier are reclaimed before the stack is gone rather than whenever some
+        // other thread happens to scan for them, and before the pool they return to is dropped.
+        if !self.reclaimer.drain() {
+            // The nodes left over still go back to the pool whenever they do get reclaimed.
+            if let Some(pool) = self.pool.take() {
+                Box::leak(pool);
+            }
+        }
     }
 }
 
-impl<T: Clone> Default for Stack<T> {
-    fn default() -> Self {
-        Self::new()
+impl<T, B: Backoff> Stack<T, HazardReclaimer, B> {
+    pub fn domain(&self) -> &Domain {
+        self.reclaimer.domain()
     }
 }
 
-impl<T: Clone> Stack<T> {
-    pub fn new() -> Self {
+impl<T, R: Reclaimer, B: Backoff> Stack<T, R, B> {
+    pub fn with_reclaimer(reclaimer: R) -> Self {
+        Self::with_parts(reclaimer, None)
+    }
+
+    /// See Stack::with_pool.
+    pub fn with_reclaimer_and_pool(reclaimer: R, capacity: usize) -> Self {
+        let pool = (capacity > 0).then(|| Box::new(Pool::new(capacity)));
+        Self::with_parts(reclaimer, pool)
+    }
+
+    fn with_parts(reclaimer: R, pool: Option<Box<Pool<Node<T>>>>) -> Self {
         Self {
             head: AtomicPtr::new(std::ptr::null_mut()),
+            reclaimer,
+            pool,
             marker: PhantomData,
         }
     }
 
-    pub fn insert(&self, value: T) -> Result<&str, &str> {
-        let mut attempts = 0;
-        loop {
-            if attempts > 15 {
-                return Err("Insertion failed. Try again!");
-            }
-            let mut holder = Holder::default();
-            let guard = unsafe { holder.load_pointer(&self.head) };
-            let current_head = if let Some(ref guard) = guard {
-                guard.data
-            } else {
-                std::ptr::null_mut()
-            };
-            let new_node = Node::new(value.clone());
-            new_node.next.store(current_head, Ordering::Release);
-            let boxed = Box::into_raw(Box::new(new_node));
-            if self
-                .head
-                .compare_exchange(current_head, boxed, Ordering::AcqRel, Ordering::Relaxed)
-                .is_ok()
-            {
-                return Ok("Insertion successful!");
-            } else {
-                let owned = unsafe { Box::from_raw(boxed) };
-                std::mem::drop(owned);
-                attempts += 1;
+    fn pool(&self) -> *const Pool<Node<T>> {
+        self.pool
+            .as_deref()
+            .map_or(std::ptr::null(), |pool| pool as *const Pool<Node<T>>)
+    }
+
+    fn allocate(&self, node: Node<T>) -> *mut Node<T> {
+        match &self.pool {
+            Some(pool) => pool.allocate(node),
+            None => Box::into_raw(Box::new(node)),
+        }
+    }
+
+    pub fn reclaimer(&self) -> &R {
+        &self.reclaimer
+    }
+
+    /// The number of reclaimed nodes currently kept for reuse, always zero without a pool.
+    pub fn pooled(&self) -> usize {
+        self.pool.as_ref().map_or(0, |pool| pool.len())
+    }
+}
+
+impl<T, R: Reclaimer + Default, B: Backoff> Default for Stack<T, R, B> {
+    fn default() -> Self {
+        Self::with_reclaimer(R::default())
+    }
+}
+
+impl<T> Stack<T> {
+    pub fn new() -> Self {
+        Self::with_reclaimer(HazardReclaimer::new())
+    }
+
+    /// Creates a stack that retires its nodes into a domain of its own instead of the process
+    /// wide one.
+    pub fn with_domain(domain: Domain) -> Self {
+        Self::with_reclaimer(HazardReclaimer::with_domain(domain))
+    }
+
+    /// Creates a stack that recycles up to capacity reclaimed nodes for later insertions instead
+    /// of freeing them. A capacity of zero creates a stack without a pool, same as Stack::new.
+    pub fn with_pool(capacity: usize) -> Self {
+        Self::with_reclaimer_and_pool(HazardReclaimer::new(), capacity)
+    }
+}
+
+impl<T, R: Reclaimer, B: Backoff> Stack<T, R, B> {
+    /// Retries until the value is pushed.
+    pub fn push(&self, value: T) {
+        let node = self.new_node(value);
+        let mut current_head = self.head();
+        let mut backoff = B::default();
+        while let Err(now) = unsafe { self.link(node, current_head) } {
+            current_head = now;
+            backoff.snooze();
+        }
+    }
+
+    /// Hands the value back if every one of a bounded number of attempts lost to another thread.
+    pub fn try_push(&self, value: T) -> Result<(), PushError<T>> {
+        let node = self.new_node(value);
+        let mut current_head = self.head();
+        let mut backoff = B::default();
+        for _ in 0..ATTEMPTS {
+            match unsafe { self.link(node, current_head) } {
+                Ok(_) => return Ok(()),
+                Err(now) => {
+                    current_head = now;
+                    backoff.snooze();
+                }
             }
         }
+        // The node never got published, so the value is still ours.
+        Err(PushError::Contended(unsafe { Node::into_value(node) }))
     }
 
-    pub fn delete(&self) -> Result<T, &str> {
-        let mut attempts = 0;
+    /// Retries until either a value is popped or the stack is found empty.
+    pub fn pop(&self) -> Option<T> {
+        let mut backoff = B::default();
         loop {
-            if attempts > 15 {
-                return Err("Deletion failed. Try again!");
-            }
-            let mut holder = Holder::default();
-            let guard = unsafe { holder.load_pointer(&self.head) };
-            let current_head = if let Some(ref guard) = guard {
-                guard.data
-            } else {
-                std::ptr::null_mut()
-            };
-            if current_head.is_null() {
-                return Err("There are no elements in the list");
+            match self.unlink() {
+                Ok(value) => return Some(value),
+                Err(PopError::Empty) => return None,
+                Err(PopError::Contended) => backoff.snooze(),
             }
-            let next_head = unsafe { (*current_head).next.load(Ordering::Acquire) };
-            if self
-                .head
-                .compare_exchange(current_head, next_head, Ordering::AcqRel, Ordering::Relaxed)
-                .is_ok()
-            {
-                let value = unsafe { std::ptr::read(&(*current_head).value) };
-                let mut holder = Holder::default();
-                let wrapper =
-                    unsafe { holder.get_wrapper(&AtomicPtr::new(current_head), &DROPBOX) };
-                wrapper.expect("Has to be there").retire();
-                return Ok(value);
-            } else {
-                attempts += 1;
+        }
+    }
+
+    /// Gives up after a bounded number of attempts that all lost to another thread.
+    pub fn try_pop(&self) -> Result<T, PopError> {
+        let mut backoff = B::default();
+        for _ in 0..ATTEMPTS {
+            match self.unlink() {
+                Err(PopError::Contended) => backoff.snooze(),
+                result => return result,
             }
         }
+        Err(PopError::Contended)
+    }
+
+    pub(crate) fn new_node(&self, value: T) -> *mut Node<T> {
+        self.allocate(Node::new(value, self.pool()))
+    }
+
+    pub(crate) fn head(&self) -> *mut Node<T> {
+        self.head.load(Ordering::Acquire)
+    }
+
+    /// One attempt at pushing the node, handing back the head it lost to. One node serves all
+    /// attempts of a push. Pushing never dereferences the head, so it needs no protection, and
+    /// only the next pointer of the node has to follow it.
+    ///
+    /// SAFETY:
+    ///    The node must not have been published yet.
+    pub(crate) unsafe fn link(
+        &self,
+        node: *mut Node<T>,
+        current_head: *mut Node<T>,
+    ) -> Result<*mut Node<T>, *mut Node<T>> {
+        unsafe { (*node).next.store(current_head, Ordering::Relaxed) };
+        self.head
+            .compare_exchange(current_head, node, Ordering::AcqRel, Ordering::Acquire)
+    }
+
+    /// One attempt at popping.
+    pub(crate) fn unlink(&self) -> Result<T, PopError> {
+        let mut shield = self.reclaimer.shield();
+        let current_head = shield.protect(&self.head);
+        if current_head.is_null() {
+            return Err(PopError::Empty);
+        }
+        let next_head = unsafe { (*current_head).next.load(Ordering::Acquire) };
+        if self
+            .head
+            .compare_exchange(current_head, next_head, Ordering::AcqRel, Ordering::Relaxed)
+            .is_err()
+        {
+            return Err(PopError::Contended);
+        }
+        let value = unsafe { ManuallyDrop::take(&mut (*current_head).value) };
+        unsafe { self.reclaimer.retire(current_head) };
+        Ok(value)
     }
 }
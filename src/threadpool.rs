@@ -1,14 +1,42 @@
 use crate::Queue;
+use crate::array_queue::ArrayQueue;
+use crate::error::PushError;
 use std::panic::{self, AssertUnwindSafe};
 use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::thread::{self, JoinHandle};
 
-type Task = Box<dyn FnOnce() + Send + 'static>;
+pub type Task = Box<dyn FnOnce() + Send + 'static>;
+
+// Where submitted tasks wait for a worker. A bounded pool pushes back on producers once it has as
+// many tasks waiting as it has room for.
+enum Tasks {
+    Unbounded(Queue<Task>),
+    Bounded(ArrayQueue<Task>),
+}
+
+impl Tasks {
+    fn push(&self, task: Task) -> Result<(), PushError<Task>> {
+        match self {
+            Tasks::Unbounded(queue) => {
+                queue.enqueue(task);
+                Ok(())
+            }
+            Tasks::Bounded(queue) => queue.push(task),
+        }
+    }
+
+    fn pop(&self) -> Option<Task> {
+        match self {
+            Tasks::Unbounded(queue) => queue.dequeue(),
+            Tasks::Bounded(queue) => queue.pop(),
+        }
+    }
+}
 
 pub struct ThreadPool {
     threads: Vec<JoinHandle<()>>,
-    tasks: Arc<Queue<Task>>,
+    tasks: Arc<Tasks>,
     execution: Arc<AtomicBool>,
 }
 
@@ -29,19 +57,29 @@ impl ThreadPool {
     pub fn new(number: usize) -> ThreadPool {
         ThreadPool {
             threads: Vec::with_capacity(number),
-            tasks: Arc::new(Queue::<Task>::new()),
+            tasks: Arc::new(Tasks::Unbounded(Queue::new())),
+            execution: Arc::new(AtomicBool::new(true)),
+        }
+    }
+
+    /// Creates a pool that keeps at most capacity tasks waiting, see execute_task and
+    /// try_execute_task.
+    pub fn bounded(number: usize, capacity: usize) -> ThreadPool {
+        ThreadPool {
+            threads: Vec::with_capacity(number),
+            tasks: Arc::new(Tasks::Bounded(ArrayQueue::new(capacity))),
             execution: Arc::new(AtomicBool::new(true)),
         }
     }
 
     pub fn spawn(&mut self) {
         for _ in 0..self.threads.capacity() {
-            let queue: Arc<Queue<Task>> = Arc::clone(&self.tasks);
+            let queue: Arc<Tasks> = Arc::clone(&self.tasks);
             let execution: Arc<AtomicBool> = Arc::clone(&self.execution);
             let thread = thread::spawn(move || {
                 loop {
                     if !execution.load(Ordering::Relaxed) {
-                        while let Ok(func) = queue.dequeue() {
+                        while let Some(func) = queue.pop() {
                             // Using AssertUnwindSafe here is fine in order to make the catch_unwind
                             // succeed because we are never operating on the state of the underlying
                             // things after the error is caught.
@@ -49,7 +87,7 @@ impl ThreadPool {
                         }
                         break;
                     }
-                    if let Ok(func) = queue.dequeue() {
+                    if let Some(func) = queue.pop() {
                         // Using AssertUnwindSafe here is fine in order to make the catch_unwind
                         // succeed because we are never operating on the state of the underlying
                         // things after the error is caught.
@@ -63,11 +101,28 @@ impl ThreadPool {
         }
     }
 
+    /// The flag workers check to find out whether the pool is shutting down.
+    pub(crate) fn execution(&self) -> Arc<AtomicBool> {
+        Arc::clone(&self.execution)
+    }
+
+    /// Submits the task. On a bounded pool that is full this yields until a worker makes room.
     pub fn execute_task<T>(&self, task: T)
     where
         T: FnOnce() + Send + 'static,
     {
-        let boxed = Box::new(task);
-        self.tasks.enqueue(boxed);
+        let mut boxed: Task = Box::new(task);
+        while let Err(error) = self.tasks.push(boxed) {
+            boxed = error.into_inner();
+            thread::yield_now();
+        }
+    }
+
+    /// Submits the task unless the pool is bounded and full, in which case it is handed back.
+    pub fn try_execute_task<T>(&self, task: T) -> Result<(), PushError<Task>>
+    where
+        T: FnOnce() + Send + 'static,
+    {
+        self.tasks.push(Box::new(task))
     }
 }
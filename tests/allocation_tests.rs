@@ -0,0 +1,80 @@
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+// Counts the allocations of the current thread only, so that the test harness does not interfere.
+struct Counting;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: Counting = Counting;
+
+#[cfg(test)]
+mod scan_test {
+    use super::ALLOCATIONS;
+    use electron::sync::atomic::AtomicPtr;
+    use electron::{BoxedPointer, Doer, Domain, Holder};
+    use std::cell::Cell;
+
+    static DROPBOX: BoxedPointer = BoxedPointer::new();
+
+    fn allocations() -> usize {
+        ALLOCATIONS.with(Cell::get)
+    }
+
+    fn retire(holder: &mut Holder, count: usize) {
+        for i in 0..count {
+            let atomic = AtomicPtr::new(Box::into_raw(Box::new(i)));
+            if let Some(mut wrapper) = unsafe { holder.get_wrapper(&atomic, &DROPBOX) } {
+                wrapper.retire();
+            }
+        }
+    }
+
+    #[test]
+    fn test_scan_does_not_allocate() {
+        let domain = Domain::with_threshold(1 << 20);
+        let values: Vec<AtomicPtr<usize>> = (0..64)
+            .map(|i| AtomicPtr::new(Box::into_raw(Box::new(i))))
+            .collect();
+        let mut holders: Vec<Holder> = (0..64).map(|_| Holder::new_in(&domain)).collect();
+        let guards: Vec<_> = holders
+            .iter_mut()
+            .zip(values.iter())
+            .map(|(holder, value)| unsafe { holder.load_pointer(value) })
+            .collect();
+        let mut holder = Holder::new_in(&domain);
+        // The first scan sizes the snapshot buffer of this thread, every later one reuses it.
+        retire(&mut holder, 100);
+        domain.try_reclaim();
+        for _ in 0..10 {
+            retire(&mut holder, 100);
+            let before = allocations();
+            domain.try_reclaim();
+            assert_eq!(allocations(), before);
+            assert_eq!(domain.stats().retired, 0);
+        }
+        drop(guards);
+        for value in values {
+            let _ = unsafe { Box::from_raw(value.into_inner()) };
+        }
+    }
+}
@@ -11,12 +11,12 @@ mod stack_test {
             let new = Arc::new(Stack::new());
             let cloned1 = Arc::clone(&new);
             let cloned2 = Arc::clone(&new);
-            let _ = new.insert(5);
+            new.push(5);
             let t1 = loom::thread::spawn(move || {
-                let _ = cloned1.insert(7);
+                cloned1.push(7);
             });
             let t2 = loom::thread::spawn(move || {
-                let _ = cloned2.delete();
+                let _ = cloned2.pop();
             });
             t1.join().unwrap();
             t2.join().unwrap();
@@ -83,9 +83,94 @@ mod hazard_test {
             if let Some(mut wrapper) = unsafe { holder.swap(&atm_ptr, boxed2, &DROPBOX) } {
                 wrapper.retire();
             }
+            // Retirement only scans once the threshold is crossed, so force the scan here.
+            Holder::try_reclaim();
             assert_eq!(check.get_number_of_drops(), 1 as usize);
             let _ = unsafe { Box::from_raw(boxed2) };
             std::mem::drop(check);
         });
     }
 }
+
+#[cfg(test)]
+#[cfg(loom)]
+mod epoch_test {
+    use electron::Queue;
+    use electron::epoch::EpochReclaimer;
+    use loom::sync::Arc;
+    #[test]
+    fn test_epoch_queue() {
+        loom::model(|| {
+            let new = Arc::new(Queue::with_reclaimer(EpochReclaimer::new()));
+            let cloned1 = Arc::clone(&new);
+            let cloned2 = Arc::clone(&new);
+            new.enqueue(5);
+            let t1 = loom::thread::spawn(move || {
+                cloned1.enqueue(7);
+            });
+            let t2 = loom::thread::spawn(move || {
+                let _ = cloned2.dequeue();
+            });
+            t1.join().unwrap();
+            t2.join().unwrap();
+        });
+    }
+}
+
+#[cfg(test)]
+#[cfg(loom)]
+mod list_test {
+    use electron::list::Set;
+    use loom::sync::Arc;
+    #[test]
+    fn test_set() {
+        // Every operation walks the list with three hazards, bound the preemptions to keep the
+        // model tractable.
+        let mut builder = loom::model::Builder::new();
+        builder.preemption_bound = Some(3);
+        builder.check(|| {
+            let new = Arc::new(Set::new());
+            let cloned1 = Arc::clone(&new);
+            let cloned2 = Arc::clone(&new);
+            new.insert(1);
+            new.insert(3);
+            let t1 = loom::thread::spawn(move || {
+                cloned1.insert(2);
+            });
+            let t2 = loom::thread::spawn(move || {
+                assert!(cloned2.remove(&1));
+            });
+            t1.join().unwrap();
+            t2.join().unwrap();
+            assert!(new.contains(&2));
+            assert!(!new.contains(&1));
+            assert!(new.contains(&3));
+        });
+    }
+}
+
+#[cfg(test)]
+#[cfg(loom)]
+mod array_queue_test {
+    use electron::ArrayQueue;
+    use loom::sync::Arc;
+    #[test]
+    fn test_array_queue() {
+        loom::model(|| {
+            let new = Arc::new(ArrayQueue::new(2));
+            let cloned1 = Arc::clone(&new);
+            let cloned2 = Arc::clone(&new);
+            new.push(5).unwrap();
+            let t1 = loom::thread::spawn(move || {
+                cloned1.push(7).unwrap();
+            });
+            let t2 = loom::thread::spawn(move || {
+                assert_eq!(cloned2.pop(), Some(5));
+            });
+            t1.join().unwrap();
+            t2.join().unwrap();
+            assert_eq!(new.pop(), Some(7));
+            assert_eq!(new.pop(), None);
+        });
+    }
+}
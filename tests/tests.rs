@@ -1,22 +1,993 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+// Counts its own drops into the shared counter, for tests checking that every value is dropped
+// exactly once.
+struct CountDrops(Arc<AtomicUsize>);
+
+impl Drop for CountDrops {
+    fn drop(&mut self) {
+        self.0.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
 #[cfg(test)]
 mod stack_test {
-    use electron::Stack;
+    use electron::backoff::{Backoff, Exponential, NoBackoff, SpinThenYield};
+    use electron::hazard::HazardReclaimer;
+    use electron::{Domain, PopError, Stack};
+    use std::sync::atomic::{AtomicUsize, Ordering};
     #[test]
     fn test_one() {
         let new = &Stack::new();
         std::thread::scope(|s| {
             for i in 0..500 {
                 s.spawn(move || {
-                    let _ = new.insert(i);
+                    new.push(i);
                 });
             }
         });
         std::thread::scope(|s| {
             for _ in 0..500 {
                 s.spawn(move || {
-                    let _ = new.delete();
+                    let _ = new.pop();
+                });
+            }
+        });
+    }
+
+    #[test]
+    fn test_empty_delete_releases_slot() {
+        let new = Stack::<usize>::with_domain(Domain::new());
+        for _ in 0..2_000_000 {
+            assert!(new.pop().is_none());
+        }
+        assert_eq!(new.domain().hazard_slots(), 1);
+    }
+
+    #[test]
+    fn test_without_clone() {
+        let new = Stack::<Box<dyn FnOnce() -> usize + Send>>::new();
+        for i in 0..10 {
+            new.push(Box::new(move || i));
+        }
+        let task = new.pop().unwrap();
+        assert_eq!(task(), 9);
+    }
+
+    #[test]
+    fn test_try_push_and_pop() {
+        let new = Stack::new();
+        assert_eq!(new.try_pop(), Err(PopError::Empty));
+        assert_eq!(new.try_push(1), Ok(()));
+        assert_eq!(new.try_pop(), Ok(1));
+    }
+
+    fn push_and_pop<B: Backoff + Send>() {
+        let new = Stack::<usize, HazardReclaimer, B>::default();
+        std::thread::scope(|s| {
+            for t in 0..8 {
+                let new = &new;
+                s.spawn(move || {
+                    for i in 0..1000 {
+                        new.push(t * 1000 + i);
+                    }
+                });
+            }
+        });
+        let popped = AtomicUsize::new(0);
+        std::thread::scope(|s| {
+            for _ in 0..8 {
+                let (new, popped) = (&new, &popped);
+                s.spawn(move || {
+                    while let Some(value) = new.pop() {
+                        popped.fetch_add(value, Ordering::Relaxed);
+                    }
+                });
+            }
+        });
+        assert_eq!(popped.load(Ordering::Relaxed), (0..8000).sum::<usize>());
+    }
+
+    #[test]
+    fn test_backoffs() {
+        push_and_pop::<Exponential>();
+        push_and_pop::<SpinThenYield>();
+        push_and_pop::<NoBackoff>();
+    }
+}
+
+#[cfg(test)]
+mod hzatomic_test {
+    use electron::{Holder, HzAtomic};
+    #[test]
+    fn test_store_and_load() {
+        let new = &HzAtomic::new(Some(Box::new(0)));
+        std::thread::scope(|s| {
+            for i in 1..=8 {
+                s.spawn(move || {
+                    new.store(Some(Box::new(i)));
+                });
+                s.spawn(move || {
+                    let mut holder = Holder::default();
+                    let guard = new.load(&mut holder).expect("Never stored as null");
+                    assert!(*guard <= 8);
+                });
+            }
+        });
+        Holder::try_reclaim();
+    }
+}
+
+#[cfg(test)]
+mod deleter_test {
+    use electron::sync::atomic::AtomicPtr;
+    use electron::{Doer, Domain, Holder};
+    use std::sync::{Arc, Mutex};
+    #[test]
+    fn test_closure_deleter() {
+        let domain = Domain::new();
+        let pool = Arc::new(Mutex::new(Vec::new()));
+        let atomic = AtomicPtr::new(Box::into_raw(Box::new(5)));
+        let mut holder = Holder::new_in(&domain);
+        let returned = Arc::clone(&pool);
+        let deleter = move |ptr: *mut i32| {
+            returned.lock().unwrap().push(unsafe { Box::from_raw(ptr) });
+        };
+        let wrapper = unsafe { holder.get_wrapper(&atomic, deleter) };
+        wrapper.expect("Was not null").retire();
+        domain.try_reclaim();
+        assert_eq!(
+            *pool.lock().unwrap().pop().expect("Returned to the pool"),
+            5
+        );
+    }
+}
+
+#[cfg(test)]
+mod queue_test {
+    use super::CountDrops;
+    use electron::Queue;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    #[test]
+    fn test_drop_runs_destructors() {
+        let drops = Arc::new(AtomicUsize::new(0));
+        let new = Queue::new();
+        for _ in 0..10 {
+            new.enqueue(CountDrops(Arc::clone(&drops)));
+        }
+        for _ in 0..5 {
+            let _ = new.dequeue();
+        }
+        std::mem::drop(new);
+        assert_eq!(drops.load(Ordering::Relaxed), 10);
+    }
+    #[test]
+    fn test_empty_dequeue() {
+        let new = Queue::new();
+        assert_eq!(new.dequeue(), None);
+        new.enqueue(1);
+        assert_eq!(new.dequeue(), Some(1));
+        assert_eq!(new.dequeue(), None);
+    }
+}
+
+#[cfg(test)]
+mod cohort_test {
+    use super::CountDrops;
+    use electron::hazard::{Cohort, HazardReclaimer, Retirable, RetireLink};
+    use electron::sync::atomic::AtomicPtr;
+    use electron::{Domain, Holder, Queue};
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    use std::sync::{Arc, Barrier};
+    use std::time::Duration;
+    struct Node {
+        _value: CountDrops,
+        link: RetireLink,
+    }
+    unsafe impl Retirable for Node {
+        fn retire_link(&self) -> &RetireLink {
+            &self.link
+        }
+    }
+    #[test]
+    fn test_drop_drains_cohort() {
+        // The threshold keeps retiring threads from reclaiming anything themselves.
+        let domain = Arc::new(Domain::with_threshold(100));
+        let queue = Queue::with_reclaimer(HazardReclaimer::with_shared_domain(Arc::clone(&domain)));
+        for i in 0..10 {
+            queue.enqueue(i);
+            assert_eq!(queue.dequeue(), Some(i));
+        }
+        assert_eq!(domain.stats().retired, 10);
+        std::mem::drop(queue);
+        assert_eq!(domain.stats().retired, 0);
+    }
+    #[test]
+    fn test_drain_waits_for_hazards() {
+        let drops = Arc::new(AtomicUsize::new(0));
+        let domain = Domain::with_threshold(100);
+        let cohort = Cohort::new();
+        let atomic = AtomicPtr::new(Box::into_raw(Box::new(Node {
+            _value: CountDrops(Arc::clone(&drops)),
+            link: RetireLink::new(),
+        })));
+        let protected = Barrier::new(2);
+        let released = AtomicBool::new(false);
+        std::thread::scope(|s| {
+            s.spawn(|| {
+                let mut holder = Holder::new_in(&domain);
+                let guard = unsafe { holder.load_pointer(&atomic) };
+                protected.wait();
+                std::thread::sleep(Duration::from_millis(50));
+                released.store(true, Ordering::Release);
+                std::mem::drop(guard);
+            });
+            protected.wait();
+            let node = atomic.swap(std::ptr::null_mut(), Ordering::AcqRel);
+            unsafe { domain.retire_in(&cohort, node) };
+            assert_eq!(cohort.pending(), 1);
+            domain.drain(&cohort);
+            // Only the reader letting go of its hazard can have let the drain finish.
+            assert!(released.load(Ordering::Acquire));
+            assert_eq!(cohort.pending(), 0);
+            assert_eq!(drops.load(Ordering::Relaxed), 1);
+        });
+    }
+    #[test]
+    fn test_cohort_dropped_first() {
+        let drops = Arc::new(AtomicUsize::new(0));
+        let domain = Domain::with_threshold(100);
+        let cohort = Cohort::new();
+        let node = Box::into_raw(Box::new(Node {
+            _value: CountDrops(Arc::clone(&drops)),
+            link: RetireLink::new(),
+        }));
+        unsafe { domain.retire_in(&cohort, node) };
+        // The retired node keeps the counter alive past the cohort.
+        std::mem::drop(cohort);
+        domain.try_reclaim();
+        assert_eq!(drops.load(Ordering::Relaxed), 1);
+    }
+}
+
+#[cfg(test)]
+mod epoch_test {
+    use super::CountDrops;
+    use electron::Queue;
+    use electron::epoch::{self, Collector, EpochReclaimer};
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    #[test]
+    fn test_epoch_queue() {
+        let drops = Arc::new(AtomicUsize::new(0));
+        let new = Queue::with_reclaimer(EpochReclaimer::new());
+        std::thread::scope(|s| {
+            for _ in 0..4 {
+                s.spawn(|| {
+                    for _ in 0..1000 {
+                        new.enqueue(CountDrops(Arc::clone(&drops)));
+                        let _ = new.dequeue();
+                    }
+                });
+            }
+        });
+        assert_eq!(drops.load(Ordering::Relaxed), 4000);
+        for _ in 0..10 {
+            new.enqueue(CountDrops(Arc::clone(&drops)));
+        }
+        std::mem::drop(new);
+        assert_eq!(drops.load(Ordering::Relaxed), 4010);
+    }
+    #[test]
+    fn test_drop_while_pinned() {
+        let drops = Arc::new(AtomicUsize::new(0));
+        let guard = epoch::pin();
+        let new = Queue::with_reclaimer_and_pool(EpochReclaimer::new(), 8);
+        for _ in 0..100 {
+            new.enqueue(CountDrops(Arc::clone(&drops)));
+            let _ = new.dequeue();
+        }
+        // The pin keeps the dequeued nodes from being reclaimed, which must not hang the drop.
+        std::mem::drop(new);
+        std::mem::drop(guard);
+        for _ in 0..3 {
+            Collector::global().collect();
+        }
+        assert_eq!(drops.load(Ordering::Relaxed), 100);
+    }
+    #[test]
+    fn test_drop_while_pinned_in_two_collectors() {
+        let own = Queue::<i32, _>::with_reclaimer(EpochReclaimer::with_collector(Collector::new()));
+        let global = Queue::with_reclaimer(EpochReclaimer::new());
+        for i in 0..100 {
+            global.enqueue(i);
+            let _ = global.dequeue();
+        }
+        let first = own.reclaimer().collector().pin();
+        let second = epoch::pin();
+        // The thread is still pinned in the global collector after leaving its own.
+        std::mem::drop(first);
+        std::mem::drop(global);
+        std::mem::drop(second);
+    }
+}
+
+#[cfg(test)]
+mod stats_test {
+    use electron::sync::atomic::AtomicPtr;
+    use electron::{BoxedPointer, Doer, Domain, Holder};
+    static DROPBOX: BoxedPointer = BoxedPointer::new();
+    #[test]
+    fn test_stats() {
+        let domain = Domain::with_threshold(100);
+        let protected = AtomicPtr::new(Box::into_raw(Box::new(1)));
+        let unprotected = AtomicPtr::new(Box::into_raw(Box::new(2)));
+        let mut reader = Holder::new_in(&domain);
+        let guard = unsafe { reader.load_pointer(&protected) };
+        let mut writer = Holder::new_in(&domain);
+        for atomic in [&protected, &unprotected] {
+            if let Some(mut wrapper) = unsafe { writer.get_wrapper(atomic, &DROPBOX) } {
+                wrapper.retire();
+            }
+        }
+        let stats = domain.stats();
+        assert_eq!(stats.hazard_slots, 1);
+        assert_eq!(stats.hazards_in_use, 1);
+        assert_eq!(stats.retired, 2);
+        assert_eq!(stats.scans, 0);
+        domain.try_reclaim();
+        let stats = domain.stats();
+        assert_eq!(stats.retired, 1);
+        assert_eq!(stats.reclaimed, 1);
+        assert_eq!(stats.scans, 1);
+        std::mem::drop(guard);
+        std::mem::drop(reader);
+        domain.try_reclaim();
+        let stats = domain.stats();
+        assert_eq!(stats.hazards_in_use, 0);
+        assert_eq!(stats.retired, 0);
+        assert_eq!(stats.reclaimed, 2);
+    }
+}
+
+#[cfg(test)]
+mod teardown_test {
+    use super::CountDrops;
+    use electron::sync::atomic::AtomicPtr;
+    use electron::{BoxedPointer, Doer, Domain, Holder};
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    static DROPBOX: BoxedPointer = BoxedPointer::new();
+    // Set in the environment of a test that was rerun in a process of its own.
+    const ISOLATED: &str = "ELECTRON_ISOLATED_TEST";
+    // Tests that need the process wide domain to be quiescent rerun themselves alone in a child
+    // process. Returns true in that child, and in the parent once the child has passed.
+    pub(crate) fn isolated(test: &str) -> bool {
+        if std::env::var_os(ISOLATED).is_some() {
+            return true;
+        }
+        let output = std::process::Command::new(std::env::current_exe().unwrap())
+            .args([test, "--exact", "--test-threads=1"])
+            .env(ISOLATED, "1")
+            .output()
+            .unwrap();
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stdout)
+        );
+        false
+    }
+    fn retire_counted(holder: &mut Holder<'_>, deleted: &Arc<AtomicUsize>, value: i32) {
+        let atomic = AtomicPtr::new(Box::into_raw(Box::new(value)));
+        let deleted = Arc::clone(deleted);
+        let deleter = move |ptr: *mut i32| {
+            std::mem::drop(unsafe { Box::from_raw(ptr) });
+            deleted.fetch_add(1, Ordering::Relaxed);
+        };
+        if let Some(mut wrapper) = unsafe { holder.get_wrapper(&atomic, deleter) } {
+            wrapper.retire();
+        }
+    }
+    #[test]
+    fn test_domain_drop() {
+        let deleted = Arc::new(AtomicUsize::new(0));
+        let drops = Arc::new(AtomicUsize::new(0));
+        let domain = Domain::with_threshold(100);
+        let protected = AtomicPtr::new(Box::into_raw(Box::new(CountDrops(Arc::clone(&drops)))));
+        let mut reader = Holder::new_in(&domain);
+        let guard = unsafe { reader.load_pointer(&protected) };
+        let mut writer = Holder::new_in(&domain);
+        for value in 0..10 {
+            retire_counted(&mut writer, &deleted, value);
+        }
+        let wrapper = unsafe { writer.get_wrapper(&protected, &DROPBOX) };
+        wrapper.expect("Was not null").retire();
+        domain.try_reclaim();
+        assert_eq!(deleted.load(Ordering::Relaxed), 10);
+        assert_eq!(drops.load(Ordering::Relaxed), 0);
+        // Still retired but no longer protected, so only the drop of the domain frees it.
+        std::mem::drop(guard);
+        std::mem::drop((reader, writer));
+        assert_eq!(domain.stats().retired, 1);
+        std::mem::drop(domain);
+        assert_eq!(drops.load(Ordering::Relaxed), 1);
+    }
+    #[test]
+    fn test_global_shutdown() {
+        if !isolated("teardown_test::test_global_shutdown") {
+            return;
+        }
+        let deleted = Arc::new(AtomicUsize::new(0));
+        {
+            let mut holder = Holder::default();
+            for value in 0..10 {
+                retire_counted(&mut holder, &deleted, value);
+            }
+            // Leaves a slot in the cache of this thread.
+            let atomic = AtomicPtr::new(Box::into_raw(Box::new(0)));
+            assert!(unsafe { holder.load_pointer(&atomic) }.is_some());
+            std::mem::drop(unsafe { Box::from_raw(atomic.into_inner()) });
+        }
+        // A leaked holder keeps its slot counted as in use until the shutdown.
+        let mut leaked = Holder::default();
+        let atomic = AtomicPtr::new(Box::into_raw(Box::new(0)));
+        assert!(unsafe { leaked.load_pointer(&atomic) }.is_some());
+        std::mem::forget(leaked);
+        std::mem::drop(unsafe { Box::from_raw(atomic.into_inner()) });
+        assert_eq!(Domain::global().stats().hazards_in_use, 1);
+        unsafe { Domain::global().shutdown() };
+        assert_eq!(deleted.load(Ordering::Relaxed), 10);
+        assert_eq!(Domain::global().stats().retired, 0);
+        assert_eq!(Domain::global().stats().hazards_in_use, 0);
+        assert_eq!(Domain::global().hazard_slots(), 0);
+        // The cached slot was freed along with the domain, so a fresh one has to be allocated.
+        let atomic = AtomicPtr::new(Box::into_raw(Box::new(7)));
+        let mut holder = Holder::default();
+        assert_eq!(unsafe { holder.load_pointer(&atomic) }.as_deref(), Some(&7));
+        assert_eq!(Domain::global().hazard_slots(), 1);
+        std::mem::drop(unsafe { Box::from_raw(atomic.into_inner()) });
+    }
+}
+
+#[cfg(test)]
+mod hazard_cache_test {
+    use super::teardown_test::isolated;
+    use electron::sync::atomic::AtomicPtr;
+    use electron::{Domain, Holder, HolderArray};
+    use std::sync::{Arc, Barrier};
+    fn protect_all<const N: usize>(holders: &mut HolderArray<'_, N>) {
+        let atomic = AtomicPtr::new(Box::into_raw(Box::new(1)));
+        for index in 0..N {
+            assert!(!holders.protect(index, &atomic).is_null());
+        }
+        std::mem::drop(unsafe { Box::from_raw(atomic.into_inner()) });
+    }
+    #[test]
+    fn test_cached_slots() {
+        if !isolated("hazard_cache_test::test_cached_slots") {
+            return;
+        }
+        let (cached, exit) = (Arc::new(Barrier::new(2)), Arc::new(Barrier::new(2)));
+        let first = {
+            let (cached, exit) = (Arc::clone(&cached), Arc::clone(&exit));
+            std::thread::spawn(move || {
+                let atomic = AtomicPtr::new(Box::into_raw(Box::new(1)));
+                for _ in 0..100 {
+                    let mut holder = Holder::default();
+                    assert!(unsafe { holder.load_pointer(&atomic) }.is_some());
+                }
+                // Every holder took the slot the one before it left in the cache.
+                assert_eq!(Domain::global().hazard_slots(), 1);
+                protect_all(&mut HolderArray::<3>::default());
+                assert_eq!(Domain::global().hazard_slots(), 3);
+                std::mem::drop(unsafe { Box::from_raw(atomic.into_inner()) });
+                cached.wait();
+                exit.wait();
+            })
+        };
+        cached.wait();
+        // The slots of the first thread are parked in its cache, not free in the list.
+        std::thread::spawn(|| protect_all(&mut HolderArray::<3>::default()))
+            .join()
+            .unwrap();
+        assert_eq!(Domain::global().hazard_slots(), 6);
+        exit.wait();
+        first.join().unwrap();
+        // Both threads handed their cached slots back to the list when they exited.
+        std::thread::spawn(|| protect_all(&mut HolderArray::<6>::default()))
+            .join()
+            .unwrap();
+        assert_eq!(Domain::global().hazard_slots(), 6);
+        assert_eq!(Domain::global().stats().hazards_in_use, 0);
+    }
+}
+
+#[cfg(test)]
+mod holder_array_test {
+    use electron::sync::atomic::AtomicPtr;
+    use electron::{BoxedPointer, Doer, Domain, Holder, HolderArray};
+    static DROPBOX: BoxedPointer = BoxedPointer::new();
+    #[test]
+    fn test_swap_keeps_protection() {
+        let domain = Domain::with_threshold(100);
+        let first = AtomicPtr::new(Box::into_raw(Box::new(1)));
+        let second = AtomicPtr::new(Box::into_raw(Box::new(2)));
+        let mut holders = HolderArray::<2>::new_in(&domain);
+        assert_eq!(domain.hazard_slots(), 2);
+        let protected = holders.protect(0, &first);
+        holders.swap(0, 1);
+        assert_eq!(
+            holders.protect(0, &second),
+            second.load(std::sync::atomic::Ordering::Acquire)
+        );
+        let mut holder = Holder::new_in(&domain);
+        if let Some(mut wrapper) = unsafe { holder.get_wrapper(&first, &DROPBOX) } {
+            wrapper.retire();
+        }
+        domain.try_reclaim();
+        assert_eq!(domain.stats().reclaimed, 0);
+        assert_eq!(unsafe { *protected }, 1);
+        holders.reset(1);
+        domain.try_reclaim();
+        assert_eq!(domain.stats().reclaimed, 1);
+        std::mem::drop(holders);
+        let _ = HolderArray::<2>::new_in(&domain);
+        assert_eq!(domain.hazard_slots(), 2);
+        let _ = unsafe { Box::from_raw(second.into_inner()) };
+    }
+}
+
+#[cfg(test)]
+mod background_test {
+    use electron::hazard::HazardReclaimer;
+    use electron::sync::atomic::AtomicPtr;
+    use electron::threadpool::ThreadPool;
+    use electron::{BackgroundReclaimer, BoxedPointer, Doer, Domain, Holder, Queue};
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
+    static DROPBOX: BoxedPointer = BoxedPointer::new();
+    fn retire_one(domain: &Domain) {
+        let atomic = AtomicPtr::new(Box::into_raw(Box::new(5)));
+        let mut holder = Holder::new_in(domain);
+        if let Some(mut wrapper) = unsafe { holder.get_wrapper(&atomic, &DROPBOX) } {
+            wrapper.retire();
+        }
+    }
+    fn wait_for_reclaimed(domain: &Domain, reclaimed: usize) {
+        let start = Instant::now();
+        while domain.stats().reclaimed < reclaimed {
+            assert!(start.elapsed() < Duration::from_secs(10));
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    }
+    #[test]
+    fn test_dedicated_thread() {
+        let domain = Arc::new(Domain::with_threshold(0));
+        // The interval is long enough that only the threshold can have woken the reclaimer.
+        let reclaimer = BackgroundReclaimer::start(Arc::clone(&domain), Duration::from_secs(60));
+        retire_one(&domain);
+        wait_for_reclaimed(&domain, 1);
+        reclaimer.stop();
+        retire_one(&domain);
+        assert_eq!(domain.stats().reclaimed, 2);
+    }
+    #[test]
+    fn test_thread_pool() {
+        let domain = Arc::new(Domain::with_threshold(0));
+        let mut pool = ThreadPool::new(2);
+        pool.spawn();
+        let reclaimer =
+            BackgroundReclaimer::start_on(&pool, Arc::clone(&domain), Duration::from_millis(1));
+        retire_one(&domain);
+        wait_for_reclaimed(&domain, 1);
+        std::mem::drop(reclaimer);
+        std::mem::drop(pool);
+    }
+    #[test]
+    fn test_pool_without_workers() {
+        let domain = Arc::new(Domain::with_threshold(0));
+        let pool = ThreadPool::new(1);
+        let reclaimer =
+            BackgroundReclaimer::start_on(&pool, Arc::clone(&domain), Duration::from_millis(1));
+        // Nothing runs the reclaimer yet, so retiring threads keep scanning themselves.
+        for reclaimed in 1..=10 {
+            retire_one(&domain);
+            assert_eq!(domain.stats().reclaimed, reclaimed);
+        }
+        std::mem::drop(reclaimer);
+        std::mem::drop(pool);
+    }
+    #[test]
+    fn test_owned_domain() {
+        let domain = Arc::new(Domain::with_threshold(0));
+        let queue = Queue::with_reclaimer(HazardReclaimer::with_shared_domain(Arc::clone(&domain)));
+        let reclaimer = BackgroundReclaimer::start(Arc::clone(&domain), Duration::from_secs(60));
+        for i in 0..10 {
+            queue.enqueue(i);
+            assert_eq!(queue.dequeue(), Some(i));
+        }
+        wait_for_reclaimed(&domain, 10);
+        reclaimer.stop();
+        std::mem::drop(queue);
+        assert_eq!(Arc::strong_count(&domain), 1);
+    }
+}
+
+#[cfg(test)]
+mod list_test {
+    use electron::list::{List, Set};
+    #[test]
+    fn test_list() {
+        let list = List::new();
+        assert!(list.insert(3, "three"));
+        assert!(list.insert(1, "one"));
+        assert!(!list.insert(3, "tres"));
+        let three = list.get(&3).unwrap();
+        assert!(list.remove(&3));
+        assert!(!list.remove(&3));
+        assert_eq!(*three, "three");
+        assert!(list.get(&3).is_none());
+        assert!(list.contains(&1));
+    }
+    #[test]
+    fn test_set_concurrent() {
+        let set = Set::new();
+        std::thread::scope(|s| {
+            for t in 0..4 {
+                let set = &set;
+                s.spawn(move || {
+                    for i in 0..1000 {
+                        assert!(set.insert(i * 4 + t));
+                        if i % 2 == 0 {
+                            assert!(set.remove(&(i * 4 + t)));
+                        }
+                    }
+                });
+            }
+        });
+        for i in 0..4000 {
+            assert_eq!(set.contains(&i), (i / 4) % 2 == 1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod hashmap_test {
+    use super::CountDrops;
+    use electron::hashmap::HashMap;
+    use std::hash::{BuildHasher, Hasher};
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    #[test]
+    fn test_hashmap() {
+        let map = HashMap::new();
+        std::thread::scope(|s| {
+            for t in 0..4 {
+                let map = &map;
+                s.spawn(move || {
+                    for i in 0..1000 {
+                        assert!(map.insert(i * 4 + t, i));
+                        if i % 2 == 0 {
+                            assert!(map.remove(&(i * 4 + t)));
+                        }
+                    }
+                });
+            }
+        });
+        assert_eq!(map.len(), 2000);
+        for i in 0..4000 {
+            match map.get(&i) {
+                Some(value) => assert_eq!((*value, (i / 4) % 2), (i / 4, 1)),
+                None => assert_eq!((i / 4) % 2, 0),
+            }
+        }
+    }
+    // Every key hashes the same, so all of them share one bucket and one split key.
+    #[derive(Default)]
+    struct Colliding;
+    impl Hasher for Colliding {
+        fn finish(&self) -> u64 {
+            7
+        }
+        fn write(&mut self, _: &[u8]) {}
+    }
+    impl BuildHasher for Colliding {
+        type Hasher = Colliding;
+        fn build_hasher(&self) -> Colliding {
+            Colliding
+        }
+    }
+    #[test]
+    fn test_colliding_keys() {
+        let map = HashMap::with_hasher(Colliding);
+        for i in 0..10 {
+            assert!(map.insert(i, i * 10));
+        }
+        assert!(!map.insert(3, 0));
+        assert!(map.remove(&3));
+        assert!(!map.contains_key(&3));
+        assert_eq!(*map.get(&4).unwrap(), 40);
+    }
+    #[test]
+    fn test_removed_values_outlive_guards_but_not_the_map() {
+        let drops = Arc::new(AtomicUsize::new(0));
+        let map = HashMap::new();
+        for i in 0..10 {
+            assert!(map.insert(i, CountDrops(Arc::clone(&drops))));
+        }
+        let guard = map.get(&0).unwrap();
+        assert!(map.remove(&0));
+        map.domain().try_reclaim();
+        assert_eq!(drops.load(Ordering::Relaxed), 0);
+        assert!(Arc::ptr_eq(&guard.0, &drops));
+        drop(guard);
+        for i in 1..5 {
+            assert!(map.remove(&i));
+        }
+        drop(map);
+        assert_eq!(drops.load(Ordering::Relaxed), 10);
+    }
+}
+
+#[cfg(test)]
+mod skipmap_test {
+    use electron::skipmap::SkipMap;
+    #[test]
+    fn test_skipmap() {
+        let map = SkipMap::new();
+        for i in [5, 1, 9, 3, 7] {
+            assert!(map.insert(i, i * 10));
+        }
+        assert!(!map.insert(3, 0));
+        assert_eq!(*map.get(&7).unwrap(), 70);
+        assert_eq!(*map.first().unwrap().key(), 1);
+        assert_eq!(*map.last().unwrap().value(), 90);
+        let keys: Vec<i32> = map.range(3..9).map(|entry| *entry.key()).collect();
+        assert_eq!(keys, [3, 5, 7]);
+        let keys: Vec<i32> = map.range(4..=9).map(|entry| *entry.key()).collect();
+        assert_eq!(keys, [5, 7, 9]);
+        let first = map.pop_first().unwrap();
+        assert!(map.remove(&9));
+        assert_eq!((*first.key(), *first.value()), (1, 10));
+        assert!(!map.contains_key(&1));
+        assert_eq!(map.last().unwrap().key(), &7);
+        assert_eq!(map.len(), 3);
+    }
+    #[test]
+    fn test_skipmap_concurrent() {
+        let map = SkipMap::new();
+        std::thread::scope(|s| {
+            for t in 0..4 {
+                let map = &map;
+                s.spawn(move || {
+                    for i in 0..1000 {
+                        assert!(map.insert(i * 4 + t, i));
+                        if i % 2 == 0 {
+                            assert!(map.remove(&(i * 4 + t)));
+                        }
+                    }
+                });
+            }
+            // Keys come out in order while the writers are busy.
+            let map = &map;
+            s.spawn(move || {
+                for _ in 0..100 {
+                    let keys: Vec<i32> = map.iter().map(|entry| *entry.key()).collect();
+                    assert!(keys.windows(2).all(|pair| pair[0] < pair[1]));
+                }
+            });
+        });
+        assert_eq!(map.len(), 2000);
+        for i in 0..4000 {
+            assert_eq!(map.contains_key(&i), (i / 4) % 2 == 1);
+        }
+        let mut popped = Vec::new();
+        while let Some(entry) = map.pop_first() {
+            popped.push(*entry.key());
+        }
+        assert!(
+            popped
+                .into_iter()
+                .eq((0..4000).filter(|i| (i / 4) % 2 == 1))
+        );
+        assert!(map.is_empty());
+    }
+}
+
+#[cfg(test)]
+mod array_queue_test {
+    use electron::threadpool::ThreadPool;
+    use electron::{ArrayQueue, PopError, PushError};
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    #[test]
+    fn test_full_and_empty() {
+        let queue = ArrayQueue::new(3);
+        assert_eq!(queue.pop(), None);
+        for i in 0..3 {
+            assert_eq!(queue.push(i), Ok(()));
+        }
+        assert!(queue.is_full());
+        assert_eq!(queue.push(3), Err(PushError::Full(3)));
+        assert_eq!(queue.pop(), Some(0));
+        assert_eq!(queue.push(3), Ok(()));
+        let values: Vec<i32> = std::iter::from_fn(|| queue.pop()).collect();
+        assert_eq!(values, [1, 2, 3]);
+        assert!(queue.is_empty());
+    }
+    #[test]
+    fn test_errors() {
+        let queue = ArrayQueue::new(1);
+        queue.push(1).unwrap();
+        let full = queue.push(2).unwrap_err();
+        assert_eq!(full.to_string(), "pushing onto a full structure");
+        assert_eq!(full.into_inner(), 2);
+        let error: Box<dyn std::error::Error> = Box::new(PopError::Empty);
+        assert_eq!(error.to_string(), "popping from an empty structure");
+    }
+    #[test]
+    fn test_concurrent() {
+        let queue = ArrayQueue::new(16);
+        let popped = AtomicUsize::new(0);
+        std::thread::scope(|s| {
+            for t in 0..4 {
+                let queue = &queue;
+                s.spawn(move || {
+                    for i in 0..1000 {
+                        let mut value = t * 1000 + i;
+                        while let Err(error) = queue.push(value) {
+                            value = error.into_inner();
+                            std::thread::yield_now();
+                        }
+                    }
+                });
+            }
+            for _ in 0..4 {
+                let (queue, popped) = (&queue, &popped);
+                s.spawn(move || {
+                    let mut received = 0;
+                    while received < 1000 {
+                        match queue.pop() {
+                            Some(value) => {
+                                popped.fetch_add(value, Ordering::Relaxed);
+                                received += 1;
+                            }
+                            None => std::thread::yield_now(),
+                        }
+                    }
+                });
+            }
+        });
+        assert_eq!(popped.load(Ordering::Relaxed), (0..4000).sum::<usize>());
+    }
+    #[test]
+    fn test_bounded_pool() {
+        let pool = ThreadPool::bounded(1, 2);
+        // Without workers nothing drains the queue.
+        for _ in 0..2 {
+            assert!(pool.try_execute_task(|| {}).is_ok());
+        }
+        assert!(pool.try_execute_task(|| {}).is_err());
+        let mut pool = pool;
+        pool.spawn();
+        let done = Arc::new(AtomicUsize::new(0));
+        for _ in 0..100 {
+            let done = Arc::clone(&done);
+            pool.execute_task(move || {
+                done.fetch_add(1, Ordering::Relaxed);
+            });
+        }
+        std::mem::drop(pool);
+        assert_eq!(done.load(Ordering::Relaxed), 100);
+    }
+    #[test]
+    fn test_single_slot() {
+        let queue = ArrayQueue::new(1);
+        for i in 0..3 {
+            assert_eq!(queue.push(i), Ok(()));
+            assert_eq!(queue.push(i), Err(PushError::Full(i)));
+            assert_eq!(queue.len(), 1);
+            assert_eq!(queue.pop(), Some(i));
+            assert_eq!(queue.pop(), None);
+        }
+    }
+}
+
+#[cfg(test)]
+mod pool_test {
+    use super::CountDrops;
+    use electron::{Queue, Stack};
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    #[test]
+    fn test_pooled_queue() {
+        let drops = Arc::new(AtomicUsize::new(0));
+        let queue = Queue::with_pool(8);
+        std::thread::scope(|s| {
+            for _ in 0..4 {
+                let (queue, drops) = (&queue, &drops);
+                s.spawn(move || {
+                    for _ in 0..1000 {
+                        queue.enqueue(CountDrops(Arc::clone(drops)));
+                        let _ = queue.dequeue();
+                    }
+                });
+            }
+        });
+        // Nothing protects the dequeued nodes any more, so the pool fills up and the next enqueue
+        // takes its node from there.
+        queue.domain().try_reclaim();
+        assert_eq!(queue.pooled(), 8);
+        queue.enqueue(CountDrops(Arc::clone(&drops)));
+        assert_eq!(queue.pooled(), 7);
+        std::mem::drop(queue);
+        assert_eq!(drops.load(Ordering::Relaxed), 4001);
+    }
+    #[test]
+    fn test_pooled_stack() {
+        let stack = Stack::with_pool(8);
+        std::thread::scope(|s| {
+            for t in 0..4 {
+                let stack = &stack;
+                s.spawn(move || {
+                    for i in 0..1000 {
+                        stack.push(vec![t, i]);
+                        assert!(stack.pop().is_some());
+                    }
+                });
+            }
+        });
+        assert!(stack.pop().is_none());
+        stack.domain().try_reclaim();
+        assert_eq!(stack.pooled(), 8);
+        stack.push(vec![]);
+        assert_eq!(stack.pooled(), 7);
+        assert!(stack.pop().is_some());
+        stack.domain().try_reclaim();
+        assert_eq!(stack.pooled(), 8);
+    }
+    #[test]
+    fn test_pool_without_capacity() {
+        let queue = Queue::with_pool(0);
+        queue.enqueue(1);
+        assert_eq!(queue.dequeue(), Some(1));
+        queue.domain().try_reclaim();
+        assert_eq!(queue.pooled(), 0);
+        let stack = Stack::with_pool(0);
+        stack.push(1);
+        assert_eq!(stack.pop(), Some(1));
+        stack.domain().try_reclaim();
+        assert_eq!(stack.pooled(), 0);
+    }
+}
+
+#[cfg(test)]
+mod elimination_test {
+    use electron::EliminationStack;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    #[test]
+    fn test_elimination_stack() {
+        let stack = EliminationStack::with_width(2);
+        assert_eq!(stack.pop(), None);
+        stack.push(1);
+        stack.push(2);
+        assert_eq!(stack.pop(), Some(2));
+        let popped = AtomicUsize::new(0);
+        std::thread::scope(|s| {
+            for t in 0..8 {
+                let (stack, popped) = (&stack, &popped);
+                s.spawn(move || {
+                    for i in 0..1000 {
+                        stack.push(t * 1000 + i);
+                        popped.fetch_add(stack.pop().unwrap(), Ordering::Relaxed);
+                    }
                 });
             }
         });
+        assert_eq!(stack.pop(), Some(1));
+        assert_eq!(popped.load(Ordering::Relaxed), (0..8000).sum::<usize>());
     }
 }